@@ -2,12 +2,21 @@
 The `Api` class serves as a univeral interface to a MediaWiki API.
 */
 
+extern crate chrono;
 extern crate cookie;
 extern crate reqwest;
+extern crate urlencoding;
 
+use crate::continuation::{CategoryTreeIter, ContinuationIter};
+use crate::quickstatements::{self, QsCommand};
+use crate::wikibase::{
+    Claim, ClaimData, ClaimRank, ConstraintViolation, DataValue, EntityId, PropertyConstraint,
+    PropertyType,
+};
 use cookie::{Cookie, CookieJar};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 #[macro_export]
 /// To quickle create a hashmap.
@@ -26,6 +35,7 @@ struct MWuser {
     lgusername: String,
     lguserid: u64,
     is_logged_in: bool,
+    rights: Vec<String>,
 }
 
 impl MWuser {
@@ -35,6 +45,7 @@ impl MWuser {
             lgusername: "".into(),
             lguserid: 0,
             is_logged_in: false,
+            rights: vec![],
         }
     }
 
@@ -56,63 +67,614 @@ impl MWuser {
         }
         Ok(())
     }
+
+    /// Sets the user rights from a `meta=userinfo&uiprop=rights` result
+    pub fn set_rights_from_userinfo(&mut self, userinfo: &serde_json::Value) {
+        self.rights = match userinfo["rights"].as_array() {
+            Some(rights) => rights
+                .iter()
+                .filter_map(|r| r.as_str())
+                .map(|r| r.to_string())
+                .collect(),
+            None => vec![],
+        };
+    }
+
+    /// Checks if the user has a specific right
+    pub fn has_right(&self, right: &str) -> bool {
+        self.rights.iter().any(|r| r == right)
+    }
 }
 
-/// `Api` is the main class to interact with a MediaWiki API
+/// `LogEvent` is a single entry from `list=logevents`
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    pub logid: u64,
+    pub log_type: String,
+    pub action: String,
+    pub title: String,
+    pub ns: i32,
+    pub user: String,
+    pub timestamp: String,
+    pub comment: String,
+    pub params: Value,
+}
+
+impl LogEvent {
+    /// Creates a `LogEvent` from a single `list=logevents` result object
+    pub fn from_json(j: &Value) -> LogEvent {
+        LogEvent {
+            logid: j["logid"].as_u64().unwrap_or(0),
+            log_type: j["type"].as_str().unwrap_or("").to_string(),
+            action: j["action"].as_str().unwrap_or("").to_string(),
+            title: j["title"].as_str().unwrap_or("").to_string(),
+            ns: j["ns"].as_i64().unwrap_or(0) as i32,
+            user: j["user"].as_str().unwrap_or("").to_string(),
+            timestamp: j["timestamp"].as_str().unwrap_or("").to_string(),
+            comment: j["comment"].as_str().unwrap_or("").to_string(),
+            params: j["params"].clone(),
+        }
+    }
+}
+
+/// `PageInfo` identifies a page, as returned in most `list=` query results
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageInfo {
+    pub pageid: u64,
+    pub ns: i32,
+    pub title: String,
+}
+
+impl PageInfo {
+    /// Creates a `PageInfo` from a result object containing `pageid`, `ns`, and `title`
+    pub fn from_json(j: &Value) -> PageInfo {
+        PageInfo {
+            pageid: j["pageid"].as_u64().unwrap_or(0),
+            ns: j["ns"].as_i64().unwrap_or(0) as i32,
+            title: j["title"].as_str().unwrap_or("").to_string(),
+        }
+    }
+}
+
+/// `Revision` is a single page revision, as returned by `prop=revisions`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    pub revid: u64,
+    pub parentid: u64,
+    pub user: String,
+    pub timestamp: String,
+    pub comment: String,
+    pub size: u64,
+    pub minor: bool,
+}
+
+impl Revision {
+    /// Creates a `Revision` from a single `prop=revisions` result object
+    pub fn from_json(j: &Value) -> Revision {
+        Revision {
+            revid: j["revid"].as_u64().unwrap_or(0),
+            parentid: j["parentid"].as_u64().unwrap_or(0),
+            user: j["user"].as_str().unwrap_or("").to_string(),
+            timestamp: j["timestamp"].as_str().unwrap_or("").to_string(),
+            comment: j["comment"].as_str().unwrap_or("").to_string(),
+            size: j["size"].as_u64().unwrap_or(0),
+            minor: !j["minor"].is_null(),
+        }
+    }
+}
+
+/// `Contribution` is a single edit of a user, as returned by `list=usercontribs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contribution {
+    pub revid: u64,
+    pub parentid: u64,
+    pub page: PageInfo,
+    pub timestamp: String,
+    pub comment: String,
+    pub minor: bool,
+    pub new: bool,
+}
+
+impl Contribution {
+    /// Creates a `Contribution` from a single `list=usercontribs` result object
+    pub fn from_json(j: &Value) -> Contribution {
+        Contribution {
+            revid: j["revid"].as_u64().unwrap_or(0),
+            parentid: j["parentid"].as_u64().unwrap_or(0),
+            page: PageInfo::from_json(j),
+            timestamp: j["timestamp"].as_str().unwrap_or("").to_string(),
+            comment: j["comment"].as_str().unwrap_or("").to_string(),
+            minor: !j["minor"].is_null(),
+            new: !j["new"].is_null(),
+        }
+    }
+}
+
+/// `ContributionStats` summarizes the contributions of a user within one namespace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContributionStats {
+    pub count: u64,
+    pub added_bytes: i64,
+    pub removed_bytes: i64,
+    pub minor_count: u64,
+    pub new_page_count: u64,
+}
+
+impl ContributionStats {
+    /// Adds a single `list=usercontribs` result object to the stats
+    pub fn add_contribution(&mut self, c: &Value) {
+        self.count += 1;
+        let sizediff = c["sizediff"].as_i64().unwrap_or(0);
+        if sizediff > 0 {
+            self.added_bytes += sizediff;
+        } else {
+            self.removed_bytes -= sizediff;
+        }
+        if !c["minor"].is_null() {
+            self.minor_count += 1;
+        }
+        if !c["new"].is_null() {
+            self.new_page_count += 1;
+        }
+    }
+}
+
+/// `ParseSection` is a section of a page, as returned by `action=parse&prop=sections`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseSection {
+    pub toclevel: u32,
+    pub level: u32,
+    pub line: String,
+    pub number: String,
+    pub index: String,
+    pub byteoffset: Option<usize>,
+    pub anchor: String,
+}
+
+impl ParseSection {
+    /// Creates a `ParseSection` from a single `prop=sections` result object
+    pub fn from_json(j: &Value) -> ParseSection {
+        ParseSection {
+            toclevel: j["toclevel"].as_u64().unwrap_or(0) as u32,
+            level: j["level"]
+                .as_str()
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(0),
+            line: j["line"].as_str().unwrap_or("").to_string(),
+            number: j["number"].as_str().unwrap_or("").to_string(),
+            index: j["index"].as_str().unwrap_or("").to_string(),
+            byteoffset: j["byteoffset"].as_u64().map(|o| o as usize),
+            anchor: j["anchor"].as_str().unwrap_or("").to_string(),
+        }
+    }
+}
+
+/// `TalkSection` is a single discussion (level 2 section) on a talk page
+#[derive(Debug, Clone, PartialEq)]
+pub struct TalkSection {
+    pub heading: String,
+    pub index: u32,
+    pub last_user: Option<String>,
+    pub last_timestamp: Option<String>,
+    pub is_closed: bool,
+}
+
+impl TalkSection {
+    /// Creates a `TalkSection` from the wikitext of a section, finding the last signature.
+    /// Signature timestamps are expected in the default English format, e.g. `12:34, 5 January 2020 (UTC)`,
+    /// and are returned as MediaWiki API timestamps
+    pub fn from_wikitext(heading: &str, index: u32, wikitext: &str) -> TalkSection {
+        let last_user = ["[[User:", "[[User talk:"]
+            .iter()
+            .filter_map(|prefix| wikitext.rfind(prefix).map(|pos| pos + prefix.len()))
+            .max()
+            .and_then(|start| {
+                let name = &wikitext[start..];
+                let end = name.find(['|', ']', '/'])?;
+                Some(name[..end].trim().to_string())
+            });
+        let last_timestamp = wikitext.rfind("(UTC)").and_then(|end| {
+            let before = wikitext[..end].trim_end();
+            let colon = before.rfind(':')?;
+            let start = before[..colon]
+                .char_indices()
+                .rev()
+                .find(|(_, c)| !c.is_ascii_digit())
+                .map(|(i, c)| i + c.len_utf8())
+                .unwrap_or(0);
+            let time =
+                chrono::NaiveDateTime::parse_from_str(&before[start..], "%H:%M, %d %B %Y").ok()?;
+            Some(time.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        });
+        let lower = wikitext.to_lowercase();
+        let is_closed = [
+            "{{archive top",
+            "{{atop",
+            "{{discussion top",
+            "{{closed",
+            "{{resolved",
+        ]
+        .iter()
+        .any(|template| lower.contains(template));
+        TalkSection {
+            heading: heading.to_string(),
+            index,
+            last_user,
+            last_timestamp,
+            is_closed,
+        }
+    }
+}
+
+/// `TemplateUsage` is a template transcluded on a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateUsage {
+    pub title: String,
+    pub ns: i32,
+    pub exists: bool,
+}
+
+/// `UserActivityProfile` summarizes the activity of a user account
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserActivityProfile {
+    pub user: String,
+    pub registration: Option<String>,
+    pub edit_count: u64,
+    pub edits_per_day_last_30_days: f64,
+}
+
+/// `GrowthStats` holds the activity of a wiki within one month
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthStats {
+    pub year_month: String,
+    pub new_articles: u64,
+    pub total_edits: u64,
+    pub new_users: u64,
+}
+
+/// `FullFileInfo` combines the page, file metadata, and MediaInfo entity of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FullFileInfo {
+    pub title: String,
+    pub page_id: u64,
+    pub categories: Vec<String>,
+    pub wikitext: Option<String>,
+    pub imageinfo: Value,
+    pub mediainfo: Value,
+}
+
+/// `MoveResult` is the outcome of a single page move in `batch_move_pages`
+#[derive(Debug)]
+pub struct MoveResult {
+    pub from: String,
+    pub to: String,
+    pub success: bool,
+    pub error: Option<Box<dyn ::std::error::Error>>,
+}
+
+/// `FeedbackEntry` is a single reader feedback entry from the ArticleFeedbackv5 extension
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackEntry {
+    pub id: String,
+    pub rating: u8,
+    pub comment: String,
+    pub user: String,
+    pub timestamp: String,
+}
+
+impl FeedbackEntry {
+    /// Creates a `FeedbackEntry` from a single `list=articlefeedbackv5-view-feedback` result object
+    pub fn from_json(j: &Value) -> FeedbackEntry {
+        let id = match &j["id"] {
+            Value::String(s) => s.to_string(),
+            Value::Number(n) => n.to_string(),
+            _ => "".to_string(),
+        };
+        FeedbackEntry {
+            id,
+            rating: j["rating"].as_u64().unwrap_or(0) as u8,
+            comment: j["comment"].as_str().unwrap_or("").to_string(),
+            user: j["user"].as_str().unwrap_or("").to_string(),
+            timestamp: j["timestamp"].as_str().unwrap_or("").to_string(),
+        }
+    }
+}
+
+/// `TagInfo` describes a change tag, as returned by `list=tags`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagInfo {
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    pub hit_count: u64,
+    pub defined: bool,
+    pub active: bool,
+    pub source: Vec<String>,
+}
+
+impl TagInfo {
+    /// Creates a `TagInfo` from a single `list=tags` result object
+    pub fn from_json(j: &Value) -> TagInfo {
+        TagInfo {
+            name: j["name"].as_str().unwrap_or("").to_string(),
+            display_name: j["displayname"].as_str().unwrap_or("").to_string(),
+            description: j["description"].as_str().unwrap_or("").to_string(),
+            hit_count: j["hitcount"].as_u64().unwrap_or(0),
+            defined: !j["defined"].is_null(),
+            active: !j["active"].is_null(),
+            source: Api::json_strings(&j["source"]),
+        }
+    }
+}
+
+/// `QsResult` is the outcome of a single QuickStatements command in `wikibase_quick_statements_import`
 #[derive(Debug)]
+pub struct QsResult {
+    pub line: u32,
+    pub action: String,
+    pub success: bool,
+    pub error: Option<Box<dyn ::std::error::Error>>,
+}
+
+/// `UserGroupInfo` describes a user group, as listed in the `usergroups` site info
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserGroupInfo {
+    pub name: String,
+    pub rights: Vec<String>,
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+    pub add_self: Vec<String>,
+    pub remove_self: Vec<String>,
+}
+
+impl UserGroupInfo {
+    /// Creates a `UserGroupInfo` from a single `usergroups` site info object
+    pub fn from_json(j: &Value) -> UserGroupInfo {
+        UserGroupInfo {
+            name: j["name"].as_str().unwrap_or("").to_string(),
+            rights: Api::json_strings(&j["rights"]),
+            add: Api::json_strings(&j["add"]),
+            remove: Api::json_strings(&j["remove"]),
+            add_self: Api::json_strings(&j["add-self"]),
+            remove_self: Api::json_strings(&j["remove-self"]),
+        }
+    }
+}
+
+/// `Reference` is a single reference on a page, as stored by the Cite extension
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub name: Option<String>,
+    pub content: String,
+    pub ref_index: u32,
+}
+
+/// `EntityImportConfig` configures `import_wikidata_entity_to_wiki`: the Wikibase API to load entities from,
+/// and the templates that get a label, description, or claim value as their first parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityImportConfig {
+    pub wikibase_api_url: String,
+    pub label_template: Option<String>,
+    pub description_template: Option<String>,
+    pub property_templates: HashMap<String, String>,
+}
+
+/// `EntityUrls` are the URLs of an entity page and of its JSON and RDF (Turtle) exports
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityUrls {
+    pub page: String,
+    pub json: String,
+    pub rdf: String,
+}
+
+/// Callback for outgoing requests, with the URL and parameters
+#[cfg(feature = "debug")]
+type RequestCallback = Box<dyn Fn(&str, &HashMap<&str, &str>)>;
+
+/// Callback for incoming responses
+#[cfg(feature = "debug")]
+type ResponseCallback = Box<dyn Fn(&reqwest::Response)>;
+
+/// `Api` is the main class to interact with a MediaWiki API
 pub struct Api {
     api_url: String,
     site_info: Value,
-    client: reqwest::Client,
+    client: Arc<reqwest::Client>,
     cookie_jar: CookieJar,
     user: MWuser,
+    page_html_cache: HashMap<String, (String, String)>,
+    move_progress_callback: Option<Box<dyn Fn(usize, usize)>>,
+    dry_run: bool,
+    entity_import_config: Option<EntityImportConfig>,
+    #[cfg(feature = "debug")]
+    on_request: Option<RequestCallback>,
+    #[cfg(feature = "debug")]
+    on_response: Option<ResponseCallback>,
+}
+
+impl ::std::fmt::Debug for Api {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Api")
+            .field("api_url", &self.api_url)
+            .field("site_info", &self.site_info)
+            .field("client", &self.client)
+            .field("cookie_jar", &self.cookie_jar)
+            .field("user", &self.user)
+            .field("page_html_cache", &self.page_html_cache)
+            .field("dry_run", &self.dry_run)
+            .field("entity_import_config", &self.entity_import_config)
+            .finish()
+    }
 }
 
 impl Api {
     /// Returns a new `Api` element, and loads the MediaWiki site info from the `api_url` site.
     /// This is done both to get basic information about the site, and to test the API.
-    pub fn new(api_url: &str) -> Result<Api, Box<::std::error::Error>> {
+    pub fn new(api_url: &str) -> Result<Api, Box<dyn ::std::error::Error>> {
+        Api::new_with_client(api_url, Arc::new(reqwest::Client::builder().build()?))
+    }
+
+    /// Same as `new()`, but uses an existing http `Client`, which can be shared between `Api` instances
+    pub fn new_with_client(
+        api_url: &str,
+        client: Arc<reqwest::Client>,
+    ) -> Result<Api, Box<dyn ::std::error::Error>> {
         let mut ret = Api {
             api_url: api_url.to_string(),
             site_info: serde_json::from_str(r"{}")?,
-            client: reqwest::Client::builder().build()?,
+            client,
             cookie_jar: CookieJar::new(),
             user: MWuser::new(),
+            page_html_cache: HashMap::new(),
+            move_progress_callback: None,
+            dry_run: false,
+            entity_import_config: None,
+            #[cfg(feature = "debug")]
+            on_request: None,
+            #[cfg(feature = "debug")]
+            on_response: None,
         };
         ret.load_site_info()?;
         //            .expect("Could not load site info for API");
         Ok(ret)
     }
 
+    /// Switches this `Api` to another wiki, and loads its site info.
+    /// Cookies, the logged-in user, and cached pages of the previous wiki are discarded.
+    /// If the site info can not be loaded, this `Api` is left unchanged
+    pub fn set_api_url(&mut self, new_url: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+        let switched = Api::new_with_client(new_url, self.client.clone())?;
+        self.api_url = switched.api_url;
+        self.site_info = switched.site_info;
+        self.cookie_jar = switched.cookie_jar;
+        self.user = switched.user;
+        self.page_html_cache.clear();
+        Ok(())
+    }
+
     /// Returns a reference to the serde_json Value containing the site info
     pub fn get_site_info(&self) -> &Value {
-        return &self.site_info;
+        &self.site_info
+    }
+
+    /// Returns a serde_json Value in site info, following `path` from the top level, e.g. `["query", "general", "lang"]`.
+    /// The value is a cloned copy; `None` if there is no such value
+    pub fn get_site_info_key_value(&self, path: &[&str]) -> Option<Value> {
+        match path.iter().fold(self.get_site_info(), |v, key| &v[key]) {
+            Value::Null => None,
+            v => Some(v.clone()),
+        }
     }
 
     /// Returns a serde_json Value in site info, within the `["query"]` object.
     /// The value is a cloned copy.
     pub fn get_site_info_value(&self, k1: &str, k2: &str) -> Value {
-        let site_info = self.get_site_info();
-        site_info["query"][k1][k2].clone()
+        self.get_site_info_key_value(&["query", k1, k2])
+            .unwrap_or(Value::Null)
     }
 
     /// Returns a String from the site info, matching `["query"][k1][k2]`
     pub fn get_site_info_string(&self, k1: &str, k2: &str) -> Result<String, String> {
-        let site_info = self.get_site_info();
-        match site_info["query"][k1][k2].as_str() {
-            Some(s) => Ok(s.to_string()),
-            None => Err(format!("No 'query.{}.{}' value in site info", k1, k2)),
+        match self.get_site_info_key_value(&["query", k1, k2]) {
+            Some(Value::String(s)) => Ok(s),
+            _ => Err(format!("No 'query.{}.{}' value in site info", k1, k2)),
+        }
+    }
+
+    /// Checks if an extension (by name, e.g. `Wikibase`) is installed, according to the site info
+    pub fn is_extension_installed(&self, name: &str) -> bool {
+        match self.site_info["query"]["extensions"].as_array() {
+            Some(extensions) => extensions.iter().any(|e| e["name"].as_str() == Some(name)),
+            None => false,
+        }
+    }
+
+    /// Returns the MediaWiki version (e.g. `1.33.0-wmf.25`), from the `generator` string in the site info
+    pub fn get_mediawiki_version(&self) -> Result<String, Box<dyn ::std::error::Error>> {
+        let generator = self.get_site_info_string("general", "generator")?;
+        match generator.trim().strip_prefix("MediaWiki ") {
+            Some(version) => Ok(version.trim().to_string()),
+            None => Err(From::from(format!(
+                "Can not parse generator '{}'",
+                generator
+            ))),
+        }
+    }
+
+    /// Returns the `mwversion` value from the site info.
+    /// Falls back to `get_mediawiki_version()` if the site info has no such value
+    pub fn get_api_version(&self) -> Result<String, Box<dyn ::std::error::Error>> {
+        match self.get_site_info_string("general", "mwversion") {
+            Ok(version) => Ok(version),
+            Err(_) => self.get_mediawiki_version(),
+        }
+    }
+
+    /// Parses the major and minor number from a version string like `1.33.0-wmf.25`
+    fn parse_version(version: &str) -> Option<(u32, u32)> {
+        let mut parts = version.split(|c: char| !c.is_ascii_digit());
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some((major, minor))
+    }
+
+    /// Checks if the MediaWiki version is at least `major.minor`
+    pub fn meets_version_requirement(
+        &self,
+        major: u32,
+        minor: u32,
+    ) -> Result<bool, Box<dyn ::std::error::Error>> {
+        let version = self.get_mediawiki_version()?;
+        match Api::parse_version(&version) {
+            Some(v) => Ok(v >= (major, minor)),
+            None => Err(From::from(format!("Can not parse version '{}'", version))),
         }
     }
 
     /// Loads the site info.
     /// Should only ever be called from `new()`
-    fn load_site_info(&mut self) -> Result<&Value, Box<::std::error::Error>> {
+    fn load_site_info(&mut self) -> Result<&Value, Box<dyn ::std::error::Error>> {
         let params = hashmap!["action"=>"query","meta"=>"siteinfo","siprop"=>"general|namespaces|namespacealiases|libraries|extensions|statistics"];
         self.site_info = self.get_query_api_json(&params)?;
         Ok(&self.site_info)
     }
 
+    /// Loads additional site info properties (e.g. `magicwords` or `usergroups`), and merges them into the site info.
+    /// Properties that are already loaded are not fetched again
+    pub fn prefetch_site_info(
+        &mut self,
+        props: &[&str],
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        let missing: Vec<&str> = props
+            .iter()
+            .filter(|p| self.site_info["query"][**p].is_null())
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let siprop = missing.join("|");
+        let params = hashmap!["action"=>"query","meta"=>"siteinfo","siprop"=>siprop.as_str()];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let mut site_info = self.site_info.take();
+        self.json_merge(&mut site_info, result);
+        self.site_info = site_info;
+        Ok(())
+    }
+
+    /// Returns the `["query"][prop]` site info, loading it first if necessary
+    fn get_site_info_property(
+        &mut self,
+        prop: &str,
+    ) -> Result<&Value, Box<dyn ::std::error::Error>> {
+        self.prefetch_site_info(&[prop])?;
+        match &self.site_info["query"][prop] {
+            Value::Null => Err(From::from(format!("No '{}' in site info", prop))),
+            value => Ok(value),
+        }
+    }
+
     /// Merges two JSON objects that are MediaWiki API results.
     /// If an array already exists in the `a` object, it will be expanded with the array from the `b` object
     /// This allows for combining multiple API results via the `continue` parameter
@@ -135,14 +697,14 @@ impl Api {
     }
 
     /// Returns a token of a `token_type`, such as `login` or `csrf` (for editing)
-    pub fn get_token(&mut self, token_type: &str) -> Result<String, Box<::std::error::Error>> {
+    pub fn get_token(&mut self, token_type: &str) -> Result<String, Box<dyn ::std::error::Error>> {
         let mut params = hashmap!["action"=>"query","meta"=>"tokens"];
-        if token_type.len() != 0 {
+        if !token_type.is_empty() {
             params.insert("type", token_type);
         }
         let mut key = token_type.to_string();
-        key += &"token".to_string();
-        if token_type.len() == 0 {
+        key += "token";
+        if token_type.is_empty() {
             key = "csrftoken".into()
         }
         let x = self.get_query_api_json_all(&params)?;
@@ -153,7 +715,7 @@ impl Api {
     }
 
     /// Calls `get_token()` to return an edit token
-    pub fn get_edit_token(&mut self) -> Result<String, Box<::std::error::Error>> {
+    pub fn get_edit_token(&mut self) -> Result<String, Box<dyn ::std::error::Error>> {
         self.get_token("csrf")
     }
 
@@ -161,7 +723,20 @@ impl Api {
     pub fn get_query_api_json_all(
         &mut self,
         params: &HashMap<&str, &str>,
-    ) -> Result<Value, Box<::std::error::Error>> {
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        self.query_api_json_all_with_callback(params, |_| true)
+    }
+
+    /// Same as `get_query_api_json_all`, but calls `callback` with the merged results after each batch.
+    /// If `callback` returns `false`, no more batches are loaded, and the results so far are returned
+    pub fn query_api_json_all_with_callback<F>(
+        &mut self,
+        params: &HashMap<&str, &str>,
+        callback: F,
+    ) -> Result<Value, Box<dyn ::std::error::Error>>
+    where
+        F: Fn(&Value) -> bool,
+    {
         let mut cont = HashMap::<String, String>::new();
         let mut ret = serde_json::json!({});
         loop {
@@ -173,6 +748,9 @@ impl Api {
             cont.clear();
             let conti = result["continue"].clone();
             self.json_merge(&mut ret, result);
+            if !callback(&ret) {
+                break;
+            }
             match conti {
                 Value::Object(obj) => {
                     for (k, v) in obj {
@@ -197,19 +775,149 @@ impl Api {
         &mut self,
         params: &HashMap<&str, &str>,
         method: &str,
-    ) -> Result<Value, Box<::std::error::Error>> {
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
         let mut params = params.clone();
         params.insert("format", "json");
+        if self.dry_run && Api::is_write_request(&params) {
+            let mut logged_params = params.clone();
+            logged_params.remove("token");
+            log::info!(
+                "Dry run, not sending {} {} request: {:?}",
+                method,
+                params.get("action").unwrap_or(&""),
+                logged_params
+            );
+            return Ok(Api::dry_run_result(&params));
+        }
         let t = self.query_api_raw(&params, method)?;
         let v: Value = serde_json::from_str(&t)?;
         Ok(v)
     }
 
+    /// Returns the request that `query_api_json` would send for `params`, without sending it:
+    /// the full URL for `GET`, or the form body for `POST` (e.g. for `curl --data`).
+    /// Parameters are sorted by name, so the output is reproducible
+    pub fn debug_request(&self, params: &HashMap<&str, &str>, method: &str) -> String {
+        let mut params = params.clone();
+        params.insert("format", "json");
+        let mut pairs: Vec<(&str, &str)> = params.into_iter().collect();
+        pairs.sort_unstable();
+        let query = pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", urlencoding::encode(k), urlencoding::encode(v)))
+            .collect::<Vec<String>>()
+            .join("&");
+        if method == "POST" {
+            query
+        } else {
+            format!("{}?{}", self.api_url, query)
+        }
+    }
+
+    /// Enables or disables dry-run mode.
+    /// In dry-run mode, write requests (edits, moves, Wikibase changes etc.; anything sent with a token) are not sent,
+    /// but logged via `log::info!`, and a mock success result is returned. Read-only queries are not affected.
+    /// Created entities get placeholder IDs like `Q0`, and created claims IDs like `Q0$dry-run-0`
+    pub fn set_dry_run(&mut self, enabled: bool) {
+        self.dry_run = enabled;
+    }
+
+    /// Checks if dry-run mode is enabled
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Checks if an API request changes data on the wiki: any request with a `token` (except `action=login`),
+    /// and write actions that are sent without one
+    fn is_write_request(params: &HashMap<&str, &str>) -> bool {
+        let action = params.get("action").copied().unwrap_or("");
+        if action == "login" {
+            return false;
+        }
+        params.contains_key("token")
+            || action.starts_with("wbset")
+            || action.starts_with("wbcreate")
+            || action.starts_with("wbremove")
+            || [
+                "edit",
+                "move",
+                "delete",
+                "undelete",
+                "protect",
+                "block",
+                "unblock",
+                "upload",
+                "rollback",
+                "patrol",
+                "managetags",
+                "revisiondelete",
+                "userrights",
+                "emailuser",
+                "import",
+                "mergehistory",
+                "wbeditentity",
+                "wbmergeitems",
+                "wblinktitles",
+                "articlefeedbackv5-add-feedback",
+            ]
+            .contains(&action)
+    }
+
+    /// Returns the mock success result of a write request in dry-run mode.
+    /// For `wbeditentity`, the result has the submitted claims, with placeholder claim IDs,
+    /// and the entity ID (`Q0`, `P0`, `L0`, or `M0` for a new entity), so callers can read them as usual
+    fn dry_run_result(params: &HashMap<&str, &str>) -> Value {
+        let action = params.get("action").copied().unwrap_or("");
+        let mut ret =
+            serde_json::json!({ action: {"result": "Success"}, "success": 1, "dryrun": true });
+        if action != "wbeditentity" {
+            return ret;
+        }
+        let id = match (params.get("id"), params.get("new")) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(&"property")) => "P0".to_string(),
+            (None, Some(&"lexeme")) => "L0".to_string(),
+            (None, Some(&"mediainfo")) => "M0".to_string(),
+            (None, _) => "Q0".to_string(),
+        };
+        let data: Value = params
+            .get("data")
+            .and_then(|d| serde_json::from_str(d).ok())
+            .unwrap_or(Value::Null);
+        let submitted: Vec<Value> = match &data["claims"] {
+            Value::Array(claims) => claims.clone(),
+            Value::Object(claims) => claims
+                .values()
+                .filter_map(|c| c.as_array())
+                .flatten()
+                .cloned()
+                .collect(),
+            _ => vec![],
+        };
+        let mut claims = serde_json::json!({});
+        for (num, mut claim) in submitted.into_iter().enumerate() {
+            let property = match claim["mainsnak"]["property"].as_str() {
+                Some(property) => property.to_string(),
+                None => continue,
+            };
+            claim["id"] = serde_json::json!(format!("{}$dry-run-{}", id, num));
+            let entry = &mut claims[&property];
+            if entry.is_null() {
+                *entry = serde_json::json!([]);
+            }
+            if let Some(entry) = entry.as_array_mut() {
+                entry.push(claim);
+            }
+        }
+        ret["entity"] = serde_json::json!({"id": id, "claims": claims});
+        ret
+    }
+
     /// GET wrapper for `query_api_json`
     pub fn get_query_api_json(
         &mut self,
         params: &HashMap<&str, &str>,
-    ) -> Result<Value, Box<::std::error::Error>> {
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
         self.query_api_json(params, "GET")
     }
 
@@ -217,7 +925,7 @@ impl Api {
     pub fn post_query_api_json(
         &mut self,
         params: &HashMap<&str, &str>,
-    ) -> Result<Value, Box<::std::error::Error>> {
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
         self.query_api_json(params, "POST")
     }
 
@@ -250,7 +958,7 @@ impl Api {
         &mut self,
         params: &HashMap<&str, &str>,
         method: &str,
-    ) -> Result<String, Box<::std::error::Error>> {
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
         let api_url = self.api_url.clone();
         self.query_raw(api_url.as_str(), params, method)
     }
@@ -261,7 +969,14 @@ impl Api {
         api_url: &str,
         params: &HashMap<&str, &str>,
         method: &str,
-    ) -> Result<String, Box<::std::error::Error>> {
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        #[cfg(feature = "debug")]
+        {
+            if let Some(callback) = &self.on_request {
+                callback(api_url, params);
+            }
+        }
+
         let mut resp;
         if method == "GET" {
             resp = self
@@ -283,55 +998,3721 @@ impl Api {
             panic!("Unsupported method");
         }
 
+        #[cfg(feature = "debug")]
+        {
+            if let Some(callback) = &self.on_response {
+                callback(&resp);
+            }
+        }
+
         let t = resp.text()?;
         Ok(t)
     }
 
-    /// Performs a login against the MediaWiki API.
-    /// If successful, user information is stored in `MWuser`, and in the cookie jar
-    pub fn login(
+    /// Sets a callback that is called by `query_raw` before every request, with the URL and parameters.
+    /// Only available with the `debug` feature
+    #[cfg(feature = "debug")]
+    pub fn set_on_request(&mut self, callback: impl Fn(&str, &HashMap<&str, &str>) + 'static) {
+        self.on_request = Some(Box::new(callback));
+    }
+
+    /// Sets a callback that is called by `query_raw` after every response.
+    /// Only available with the `debug` feature
+    #[cfg(feature = "debug")]
+    pub fn set_on_response(&mut self, callback: impl Fn(&reqwest::Response) + 'static) {
+        self.on_response = Some(Box::new(callback));
+    }
+
+    /// Returns the rendered HTML of a page, via `index.php?action=render`.
+    /// The ETag of the previous response is sent as `If-None-Match`;
+    /// on `304 Not Modified`, the cached HTML is returned instead. Other non-success statuses are an `Err`
+    pub fn get_page_html_cached(
         &mut self,
-        lgname: &str,
-        lgpassword: &str,
-    ) -> Result<(), Box<::std::error::Error>> {
-        let lgtoken = self.get_token("login")?;
-        let params = hashmap!("action"=>"login","lgname"=>&lgname,"lgpassword"=>&lgpassword,"lgtoken"=>&lgtoken);
-        let res = self.post_query_api_json(&params)?;
-        if res["login"]["result"] == "Success" {
-            self.user.set_from_login(&res["login"])?;
-            Ok(())
-        } else {
-            panic!("Login failed") // TODO proper error return
+        title: &str,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let index_url = self.api_url.replace("api.php", "index.php");
+        let params = hashmap!["action"=>"render","title"=>title];
+        let mut request = self
+            .client
+            .get(index_url.as_str())
+            .header(reqwest::header::COOKIE, self.cookies_to_string())
+            .query(&params);
+        if let Some((etag, _)) = self.page_html_cache.get(title) {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag.as_str());
+        }
+        let mut resp = request.send()?;
+        self.set_cookies_from_response(&resp);
+
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return match self.page_html_cache.get(title) {
+                Some((_, html)) => Ok(html.to_string()),
+                None => Err(From::from(format!(
+                    "Got 304 Not Modified for {} without a cached page",
+                    title
+                ))),
+            };
+        }
+        if !resp.status().is_success() {
+            return Err(From::from(format!(
+                "Could not render {}: HTTP status {}",
+                title,
+                resp.status()
+            )));
         }
+
+        let html = resp.text()?;
+        match resp.headers().get(reqwest::header::ETAG) {
+            Some(etag) => {
+                let etag = etag.to_str()?.to_string();
+                self.page_html_cache
+                    .insert(title.to_string(), (etag, html.clone()));
+            }
+            None => {
+                self.page_html_cache.remove(title);
+            }
+        }
+        Ok(html)
     }
 
-    /// Performs a SPARQL query against a wikibase installation.
-    /// Tries to get the SPARQL endpoint URL from the site info
-    pub fn sparql_query(&mut self, query: &str) -> Result<Value, Box<::std::error::Error>> {
-        let query_api_url = self.get_site_info_string("general", "wikibase-sparql")?;
-        let params = hashmap!["query"=>query,"format"=>"json"];
-        let result = self.query_raw(&query_api_url, &params, "GET")?;
-        Ok(serde_json::from_str(&result)?)
+    /// Returns all log events matching the given log type, action, title, and user (all optional).
+    /// Events are ordered newest first
+    pub fn get_log_events(
+        &mut self,
+        letype: Option<&str>,
+        leaction: Option<&str>,
+        letitle: Option<&str>,
+        leuser: Option<&str>,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        let mut params = hashmap!["action"=>"query","list"=>"logevents","lelimit"=>"max","leprop"=>"ids|title|type|user|timestamp|comment|details"];
+        if let Some(letype) = letype {
+            params.insert("letype", letype);
+        }
+        if let Some(leaction) = leaction {
+            params.insert("leaction", leaction);
+        }
+        if let Some(letitle) = letitle {
+            params.insert("letitle", letitle);
+        }
+        if let Some(leuser) = leuser {
+            params.insert("leuser", leuser);
+        }
+        let result = self.get_query_api_json_all(&params)?;
+        match result["query"]["logevents"].as_array() {
+            Some(events) => Ok(events.iter().map(LogEvent::from_json).collect()),
+            None => Ok(vec![]),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::Api;
+    /// Returns the log events of type `letype` for a single page
+    fn get_page_log(
+        &mut self,
+        letype: &str,
+        title: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_log_events(Some(letype), None, Some(title), None)
+    }
 
-    #[test]
-    fn site_info() {
-        let api = Api::new("https://www.wikidata.org/w/api.php").unwrap();
-        assert_eq!(
-            api.get_site_info_string("general", "sitename").unwrap(),
-            "Wikidata"
-        );
+    /// Returns the protection log of a page
+    pub fn get_page_protection_log(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_page_log("protect", title)
     }
 
-    #[test]
-    fn sparql_query() {
-        let mut api = Api::new("https://www.wikidata.org/w/api.php").unwrap();
-        let res = api.sparql_query ( "SELECT ?q ?qLabel ?fellow_id { ?q wdt:P31 wd:Q5 ; wdt:P6594 ?fellow_id . SERVICE wikibase:label { bd:serviceParam wikibase:language '[AUTO_LANGUAGE],en'. } }" ).unwrap() ;
-        assert!(res["results"]["bindings"].as_array().unwrap().len() > 300);
+    /// Returns the move log of a page
+    pub fn get_page_move_log(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_page_log("move", title)
+    }
+
+    /// Returns the deletion log of a page
+    pub fn get_page_delete_log(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_page_log("delete", title)
+    }
+
+    /// Returns the upload log of a file; `filename` is without the `File:` prefix
+    pub fn get_page_upload_log(
+        &mut self,
+        filename: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_page_log("upload", &format!("File:{}", filename))
+    }
+
+    /// Returns the block log of a user
+    pub fn get_user_block_log(
+        &mut self,
+        user: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_page_log("block", &format!("User:{}", user))
+    }
+
+    /// Returns the user rights log of a user
+    pub fn get_user_rights_log(
+        &mut self,
+        user: &str,
+    ) -> Result<Vec<LogEvent>, Box<dyn ::std::error::Error>> {
+        self.get_page_log("rights", &format!("User:{}", user))
+    }
+
+    /// Returns the timestamp of the most recent protection (or protection change) of a page,
+    /// or `None` if the page has never been protected, or was unprotected since
+    pub fn get_page_protected_since(
+        &mut self,
+        title: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        let events = self.get_page_protection_log(title)?;
+        Ok(events
+            .first()
+            .filter(|e| e.action != "unprotect")
+            .map(|e| e.timestamp.clone()))
+    }
+
+    /// Returns the time elapsed since the most recent protection of a page,
+    /// or `None` if the page is not protected (according to its protection log)
+    pub fn get_page_protection_duration(
+        &mut self,
+        title: &str,
+    ) -> Result<Option<::std::time::Duration>, Box<dyn ::std::error::Error>> {
+        let timestamp = match self.get_page_protected_since(title)? {
+            Some(timestamp) => timestamp,
+            None => return Ok(None),
+        };
+        let protected_since = chrono::DateTime::parse_from_rfc3339(&timestamp)?;
+        let elapsed = chrono::Utc::now().signed_duration_since(protected_since);
+        Ok(Some(elapsed.to_std().unwrap_or_default()))
+    }
+
+    /// Returns statistics about all contributions of `user`, grouped by namespace ID.
+    /// `removed_bytes` is positive, i.e. the sum of all negative size differences, negated
+    pub fn get_contributions_namespace_breakdown(
+        &mut self,
+        user: &str,
+    ) -> Result<HashMap<i32, ContributionStats>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"usercontribs","ucuser"=>user,"uclimit"=>"max","ucprop"=>"ids|timestamp|sizediff|flags"];
+        let result = self.get_query_api_json_all(&params)?;
+        let mut ret: HashMap<i32, ContributionStats> = HashMap::new();
+        if let Some(contribs) = result["query"]["usercontribs"].as_array() {
+            for c in contribs {
+                let ns = c["ns"].as_i64().unwrap_or(0) as i32;
+                ret.entry(ns).or_default().add_contribution(c);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the latest `limit` contributions of `user`, each with its size difference in bytes
+    pub fn get_contributions_with_diffs(
+        &mut self,
+        user: &str,
+        limit: u32,
+    ) -> Result<Vec<(Contribution, i64)>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"usercontribs","ucuser"=>user,"uclimit"=>"max","ucprop"=>"ids|title|timestamp|sizediff|flags|comment"];
+        ContinuationIter::new(self, &params, "usercontribs")
+            .take(limit as usize)
+            .map(|c| {
+                let c = c?;
+                Ok((
+                    Contribution::from_json(&c),
+                    c["sizediff"].as_i64().unwrap_or(0),
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the sum of the size differences of all contributions of `user`.
+    /// A negative value means that the user removed more content than they added
+    pub fn get_net_bytes_contributed(
+        &mut self,
+        user: &str,
+    ) -> Result<i64, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"usercontribs","ucuser"=>user,"uclimit"=>"max","ucprop"=>"sizediff"];
+        let mut ret = 0;
+        for c in ContinuationIter::new(self, &params, "usercontribs") {
+            ret += c?["sizediff"].as_i64().unwrap_or(0);
+        }
+        Ok(ret)
+    }
+
+    /// Returns the pages on the watchlist of another user, via `list=watchlistraw`.
+    /// `watchlist_token` is the watchlist token from the preferences of that user; page IDs are not available
+    pub fn get_pages_watched_by_user(
+        &mut self,
+        username: &str,
+        watchlist_token: &str,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"watchlistraw","wrowner"=>username,"wrtoken"=>watchlist_token,"wrlimit"=>"max"];
+        ContinuationIter::new_with_path(self, &params, &["watchlistraw"])
+            .map(|page| Ok(PageInfo::from_json(&page?)))
+            .collect()
+    }
+
+    /// Returns the pages that are on the watchlists of both users
+    pub fn get_shared_watchlist(
+        &mut self,
+        user_a: &str,
+        token_a: &str,
+        user_b: &str,
+        token_b: &str,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        let pages_b: HashSet<(i32, String)> = self
+            .get_pages_watched_by_user(user_b, token_b)?
+            .into_iter()
+            .map(|page| (page.ns, page.title))
+            .collect();
+        Ok(self
+            .get_pages_watched_by_user(user_a, token_a)?
+            .into_iter()
+            .filter(|page| pages_b.contains(&(page.ns, page.title.clone())))
+            .collect())
+    }
+
+    /// Returns an iterator over the members of a category, via `list=categorymembers`.
+    /// `category` includes the namespace prefix; `cmtype` can contain `page`, `subcat`, and `file`
+    pub fn get_category_members(
+        &mut self,
+        category: &str,
+        cmtype: &[&str],
+    ) -> ContinuationIter<'_> {
+        let cmtype = cmtype.join("|");
+        let params = hashmap!["action"=>"query","list"=>"categorymembers","cmtitle"=>category,"cmtype"=>cmtype.as_str(),"cmlimit"=>"max"];
+        ContinuationIter::new(self, &params, "categorymembers")
+    }
+
+    /// Returns an iterator over the pages and files in a category and its subcategories, up to `max_depth` levels deep.
+    /// `category` includes the namespace prefix; every page is returned once, even if it is in several subcategories
+    pub fn get_pages_in_category_recursive(
+        &mut self,
+        category: &str,
+        max_depth: u32,
+    ) -> CategoryTreeIter<'_> {
+        CategoryTreeIter::new(self, category, max_depth)
+    }
+
+    /// Returns the project name without a leading `WikiProject `
+    fn wikiproject_name(project_name: &str) -> &str {
+        project_name.trim_start_matches("WikiProject ").trim()
+    }
+
+    /// Returns the user names of the members of a WikiProject.
+    /// Looks at `Category:WikiProject <name> members`, then `Category:WikiProject <name> participants`
+    pub fn get_wikiproject_members(
+        &mut self,
+        project_name: &str,
+    ) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        let name = Api::wikiproject_name(project_name);
+        for suffix in &["members", "participants"] {
+            let category = format!("Category:WikiProject {} {}", name, suffix);
+            let mut users = vec![];
+            for member in self.get_category_members(&category, &["page"]) {
+                let member = member?;
+                if member["ns"].as_i64() != Some(2) {
+                    continue;
+                }
+                if let Some(title) = member["title"].as_str() {
+                    match title.find(':') {
+                        Some(pos) => users.push(title[pos + 1..].to_string()),
+                        None => users.push(title.to_string()),
+                    }
+                }
+            }
+            if !users.is_empty() {
+                return Ok(users);
+            }
+        }
+        Ok(vec![])
+    }
+
+    /// Returns an iterator over the pages in `Category:WikiProject <name> articles`.
+    /// Note that these are usually the talk pages of the articles tagged by the project
+    pub fn get_wikiproject_articles(&mut self, project: &str) -> ContinuationIter<'_> {
+        let category = format!(
+            "Category:WikiProject {} articles",
+            Api::wikiproject_name(project)
+        );
+        let params = hashmap!["action"=>"query","list"=>"categorymembers","cmtitle"=>category.as_str(),"cmlimit"=>"max"];
+        ContinuationIter::new(self, &params, "categorymembers")
+    }
+
+    /// Returns a MediaWiki API timestamp for `days` days before now
+    fn timestamp_days_ago(days: u32) -> String {
+        let then = chrono::Utc::now() - chrono::Duration::days(i64::from(days));
+        then.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    /// Returns the revisions of a page, oldest first.
+    /// `rvstart` and `rvend` are optional timestamps limiting the revisions to that time range
+    pub fn get_revision_history(
+        &mut self,
+        title: &str,
+        rvstart: Option<&str>,
+        rvend: Option<&str>,
+    ) -> Result<Vec<Revision>, Box<dyn ::std::error::Error>> {
+        let mut params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvdir"=>"newer","rvlimit"=>"max","rvprop"=>"ids|timestamp|user|comment|size|flags"];
+        if let Some(rvstart) = rvstart {
+            params.insert("rvstart", rvstart);
+        }
+        if let Some(rvend) = rvend {
+            params.insert("rvend", rvend);
+        }
+        let result = self.get_query_api_json_all(&params)?;
+        let mut ret = vec![];
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for page in pages.values() {
+                if let Some(revisions) = page["revisions"].as_array() {
+                    ret.extend(revisions.iter().map(Revision::from_json));
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the first revision of a page from `timestamp` on, in direction `rvdir` (`older` or `newer`)
+    fn get_revision_from(
+        &mut self,
+        title: &str,
+        timestamp: &str,
+        rvdir: &str,
+    ) -> Result<Option<Revision>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvstart"=>timestamp,"rvdir"=>rvdir,"rvlimit"=>"1","rvprop"=>"ids|timestamp|user|comment|size|flags"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        Ok(result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .and_then(|page| page["revisions"].as_array())
+            .and_then(|revisions| revisions.first())
+            .map(Revision::from_json))
+    }
+
+    /// Returns the newest revision of a page at or before `timestamp`,
+    /// or `None` if the page did not exist yet at that time
+    pub fn get_revision_before(
+        &mut self,
+        title: &str,
+        timestamp: &str,
+    ) -> Result<Option<Revision>, Box<dyn ::std::error::Error>> {
+        self.get_revision_from(title, timestamp, "older")
+    }
+
+    /// Returns the oldest revision of a page at or after `timestamp`,
+    /// or `None` if the page was not edited since (or does not exist)
+    pub fn get_revision_after(
+        &mut self,
+        title: &str,
+        timestamp: &str,
+    ) -> Result<Option<Revision>, Box<dyn ::std::error::Error>> {
+        self.get_revision_from(title, timestamp, "newer")
+    }
+
+    /// Returns the average number of edits per day to a page, over the last `period_days` days
+    pub fn get_page_edit_frequency(
+        &mut self,
+        title: &str,
+        period_days: u32,
+    ) -> Result<f64, Box<dyn ::std::error::Error>> {
+        if period_days == 0 {
+            return Err(From::from("period_days must be larger than 0"));
+        }
+        let start = Api::timestamp_days_ago(period_days);
+        let revisions = self.get_revision_history(title, Some(&start), None)?;
+        Ok(revisions.len() as f64 / f64::from(period_days))
+    }
+
+    /// Returns up to `limit` pages in namespace `ns` with at least `min_freq` edits per day over the last `period_days` days,
+    /// most frequently edited first.
+    /// Based on `list=recentchanges`, which usually only covers the last 30 days
+    pub fn get_pages_by_edit_frequency(
+        &mut self,
+        ns: i32,
+        min_freq: f64,
+        period_days: u32,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        if period_days == 0 {
+            return Err(From::from("period_days must be at least 1"));
+        }
+        let ns = ns.to_string();
+        let rcend = Api::timestamp_days_ago(period_days);
+        let params = hashmap!["action"=>"query","list"=>"recentchanges","rcnamespace"=>ns.as_str(),"rcend"=>rcend.as_str(),"rctype"=>"edit|new","rcprop"=>"title|ids","rclimit"=>"max"];
+        let result = self.get_query_api_json_all(&params)?;
+        let mut counts: HashMap<u64, (PageInfo, u64)> = HashMap::new();
+        if let Some(changes) = result["query"]["recentchanges"].as_array() {
+            for change in changes {
+                let page = PageInfo::from_json(change);
+                counts.entry(page.pageid).or_insert((page, 0)).1 += 1;
+            }
+        }
+        let mut pages: Vec<(PageInfo, u64)> = counts
+            .into_values()
+            .filter(|(_, count)| *count as f64 / f64::from(period_days) >= min_freq)
+            .collect();
+        pages.sort_by_key(|(_, count)| ::std::cmp::Reverse(*count));
+        pages.truncate(limit as usize);
+        Ok(pages.into_iter().map(|(page, _)| page).collect())
+    }
+
+    /// Returns up to `limit` articles (namespace 0) with the most edits in the last `days` days, with their edit counts,
+    /// most edited first. Recent changes are streamed, but a counter for every edited article is kept in memory,
+    /// which can be large for busy wikis; recent changes only cover about 30 days
+    pub fn get_most_edited_articles(
+        &mut self,
+        limit: u32,
+        days: u32,
+    ) -> Result<Vec<(PageInfo, u64)>, Box<dyn ::std::error::Error>> {
+        let rcend = Api::timestamp_days_ago(days);
+        let params = hashmap!["action"=>"query","list"=>"recentchanges","rcnamespace"=>"0","rcend"=>rcend.as_str(),"rctype"=>"edit|new","rcprop"=>"title|ids","rclimit"=>"max"];
+        let mut counts: HashMap<u64, (PageInfo, u64)> = HashMap::new();
+        for change in ContinuationIter::new(self, &params, "recentchanges") {
+            let page = PageInfo::from_json(&change?);
+            counts.entry(page.pageid).or_insert((page, 0)).1 += 1;
+        }
+        let mut pages: Vec<(PageInfo, u64)> = counts.into_values().collect();
+        pages.sort_by_key(|(_, count)| ::std::cmp::Reverse(*count));
+        pages.truncate(limit as usize);
+        Ok(pages)
+    }
+
+    /// Returns up to `limit` users with the most edits in the last `days` days, with their edit counts,
+    /// most active first. As with `get_most_edited_articles`, a counter for every active user is kept in memory
+    pub fn get_most_active_editors(
+        &mut self,
+        limit: u32,
+        days: u32,
+    ) -> Result<Vec<(String, u64)>, Box<dyn ::std::error::Error>> {
+        let rcend = Api::timestamp_days_ago(days);
+        let params = hashmap!["action"=>"query","list"=>"recentchanges","rcend"=>rcend.as_str(),"rctype"=>"edit|new","rcprop"=>"user","rclimit"=>"max"];
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for change in ContinuationIter::new(self, &params, "recentchanges") {
+            if let Some(user) = change?["user"].as_str() {
+                *counts.entry(user.to_string()).or_insert(0) += 1;
+            }
+        }
+        let mut users: Vec<(String, u64)> = counts.into_iter().collect();
+        users.sort_by_key(|(_, count)| ::std::cmp::Reverse(*count));
+        users.truncate(limit as usize);
+        Ok(users)
+    }
+
+    /// Returns an `Err` with the error code and info if `result` is an API error response
+    pub(crate) fn check_api_error(result: &Value) -> Result<(), Box<dyn ::std::error::Error>> {
+        match result["error"]["code"].as_str() {
+            Some(code) => Err(From::from(format!(
+                "{}: {}",
+                code,
+                result["error"]["info"].as_str().unwrap_or("")
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds an edit token to `params`, and POSTs them to the API.
+    /// An API error response is returned as an `Err`
+    fn post_with_edit_token(
+        &mut self,
+        params: &HashMap<&str, &str>,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let token = self.get_edit_token()?;
+        let mut params = params.clone();
+        params.insert("token", &token);
+        let result = self.post_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        Ok(result)
+    }
+
+    /// Returns the main slot content of the first revision of the first page in a `prop=revisions` result
+    fn first_revision_content(result: &Value) -> Option<String> {
+        result["query"]["pages"]
+            .as_object()?
+            .values()
+            .filter_map(|page| page["revisions"][0]["slots"]["main"]["*"].as_str())
+            .map(|s| s.to_string())
+            .next()
+    }
+
+    /// Returns the current wikitext of a page, or `None` if the page does not exist
+    pub fn get_page_wikitext(
+        &mut self,
+        title: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvprop"=>"content","rvslots"=>"main"];
+        let result = self.get_query_api_json(&params)?;
+        Ok(Api::first_revision_content(&result))
+    }
+
+    /// Replaces the wikitext of a page, creating the page if necessary
+    pub fn edit_page(
+        &mut self,
+        title: &str,
+        text: &str,
+        summary: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"edit","title"=>title,"text"=>text,"summary"=>summary];
+        self.post_with_edit_token(&params)
+    }
+
+    /// Adds a new section to the end of a page, creating the page if necessary
+    pub fn create_section(
+        &mut self,
+        title: &str,
+        section_title: &str,
+        text: &str,
+        summary: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"edit","title"=>title,"section"=>"new","sectiontitle"=>section_title,"text"=>text,"summary"=>summary];
+        self.post_with_edit_token(&params)
+    }
+
+    /// Appends wikitext to the end of a page, creating the page if necessary
+    pub fn append_to_page(
+        &mut self,
+        title: &str,
+        text: &str,
+        summary: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"edit","title"=>title,"appendtext"=>text,"summary"=>summary];
+        self.post_with_edit_token(&params)
+    }
+
+    /// Returns the wikitext of the talk page of a user, or `None` if it does not exist
+    pub fn get_user_talk_page(
+        &mut self,
+        username: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        self.get_page_wikitext(&format!("User talk:{}", username))
+    }
+
+    /// Posts a message as a new section on the talk page of a user, creating the page if needed.
+    /// Fails without editing if a block of the current user prevents it;
+    /// partial blocks are left to the edit itself to check
+    pub fn post_user_talk_message(
+        &mut self,
+        username: &str,
+        section_title: &str,
+        message: &str,
+        summary: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","meta"=>"userinfo","uiprop"=>"blockinfo"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        if !Api::block_allows_user_talk(&result["query"]["userinfo"], username) {
+            return Err(From::from("Current user is blocked"));
+        }
+
+        let title = format!("User talk:{}", username);
+        self.create_section(&title, section_title, message, summary)?;
+        Ok(())
+    }
+
+    /// Checks if the block (if any) in a `meta=userinfo` result with `blockinfo` lets the user post on the talk page of `username`.
+    /// Sitewide blocks only allow posting on the user's own talk page, unless that is blocked too (`blockowntalk`)
+    fn block_allows_user_talk(userinfo: &Value, username: &str) -> bool {
+        let flag = |v: &Value| v.as_bool().unwrap_or(!v.is_null());
+        if userinfo["blockid"].is_null() || flag(&userinfo["blockpartial"]) {
+            return true;
+        }
+        let own_talk = userinfo["name"]
+            .as_str()
+            .map(|name| name.replace('_', " ") == username.replace('_', " "))
+            .unwrap_or(false);
+        own_talk && !flag(&userinfo["blockowntalk"])
+    }
+
+    /// Moves a page, optionally leaving a redirect behind
+    pub fn move_page(
+        &mut self,
+        from: &str,
+        to: &str,
+        reason: &str,
+        leave_redirect: bool,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let mut params = hashmap!["action"=>"move","from"=>from,"to"=>to,"reason"=>reason];
+        if !leave_redirect {
+            params.insert("noredirect", "1");
+        }
+        self.post_with_edit_token(&params)
+    }
+
+    /// Sets a callback that is called after each move in `batch_move_pages`,
+    /// with the number of moves done so far, and the total number of moves
+    pub fn set_move_progress_callback(&mut self, f: impl Fn(usize, usize) + 'static) {
+        self.move_progress_callback = Some(Box::new(f));
+    }
+
+    /// Moves a list of `(from, to)` pages.
+    /// A failed move does not stop the batch; every move is tried, and its result reported
+    pub fn batch_move_pages(
+        &mut self,
+        moves: &[(&str, &str)],
+        reason: &str,
+        leave_redirects: bool,
+    ) -> Result<Vec<MoveResult>, Box<dyn ::std::error::Error>> {
+        let mut ret = Vec::with_capacity(moves.len());
+        for (num, (from, to)) in moves.iter().enumerate() {
+            let error = self.move_page(from, to, reason, leave_redirects).err();
+            ret.push(MoveResult {
+                from: from.to_string(),
+                to: to.to_string(),
+                success: error.is_none(),
+                error,
+            });
+            if let Some(callback) = &self.move_progress_callback {
+                callback(num + 1, moves.len());
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Runs a `prop=` query (`params`) for `titles`, in batches of 50, with continuation.
+    /// Returns the page objects of all results
+    fn query_pages_batched(
+        &mut self,
+        titles: &[String],
+        params: &HashMap<&str, &str>,
+    ) -> Result<Vec<Value>, Box<dyn ::std::error::Error>> {
+        let mut ret = vec![];
+        for chunk in titles.chunks(50) {
+            let titles = chunk.join("|");
+            let mut params = params.clone();
+            params.insert("action", "query");
+            params.insert("titles", &titles);
+            let result = self.get_query_api_json_all(&params)?;
+            if let Some(pages) = result["query"]["pages"].as_object() {
+                ret.extend(pages.values().cloned());
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the titles in the `key` array of a page object, e.g. `categories` or `links`
+    fn page_titles_in(page: &Value, key: &str) -> Vec<String> {
+        match page[key].as_array() {
+            Some(arr) => arr
+                .iter()
+                .filter_map(|x| x["title"].as_str())
+                .map(|s| s.to_string())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Returns the categories of `root_titles`, and of all pages linked from them, up to `depth` hops.
+    /// The result maps page titles to category titles.
+    /// Stops following links once 1000 pages have been visited
+    pub fn get_categories_of_page_tree(
+        &mut self,
+        root_titles: &[&str],
+        depth: u32,
+    ) -> Result<HashMap<String, Vec<String>>, Box<dyn ::std::error::Error>> {
+        let max_pages = 1000;
+        let mut ret = HashMap::new();
+        let mut visited: HashSet<String> = root_titles.iter().map(|t| t.to_string()).collect();
+        let mut frontier: Vec<String> = visited.iter().cloned().collect();
+        let mut hop = 0;
+        while !frontier.is_empty() {
+            let follow_links = hop < depth;
+            let params = if follow_links {
+                hashmap!["prop"=>"categories|links","cllimit"=>"max","pllimit"=>"max"]
+            } else {
+                hashmap!["prop"=>"categories","cllimit"=>"max"]
+            };
+            let pages = self.query_pages_batched(&frontier, &params)?;
+            frontier.clear();
+            for page in pages {
+                let title = match page["title"].as_str() {
+                    Some(title) => title.to_string(),
+                    None => continue,
+                };
+                ret.insert(title, Api::page_titles_in(&page, "categories"));
+                if !follow_links {
+                    continue;
+                }
+                for link in Api::page_titles_in(&page, "links") {
+                    if visited.len() >= max_pages {
+                        break;
+                    }
+                    if visited.insert(link.clone()) {
+                        frontier.push(link);
+                    }
+                }
+            }
+            hop += 1;
+        }
+        Ok(ret)
+    }
+
+    /// Returns an `Err` unless the ArticleFeedbackv5 extension is installed
+    fn require_article_feedback(&self) -> Result<(), Box<dyn ::std::error::Error>> {
+        if self.is_extension_installed("ArticleFeedbackv5") {
+            Ok(())
+        } else {
+            Err(From::from("ArticleFeedbackv5 extension is not installed"))
+        }
+    }
+
+    /// Submits reader feedback for a page via the ArticleFeedbackv5 extension.
+    /// `rating` is from 1 to 5 stars
+    pub fn submit_feedback(
+        &mut self,
+        title: &str,
+        rating: u8,
+        comment: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        self.require_article_feedback()?;
+        if !(1..=5).contains(&rating) {
+            return Err(From::from(format!(
+                "Invalid rating {}, must be 1-5",
+                rating
+            )));
+        }
+        let rating = rating.to_string();
+        let params = hashmap!["action"=>"articlefeedbackv5-add-feedback","title"=>title,"rating"=>rating.as_str(),"comment"=>comment];
+        self.post_with_edit_token(&params)?;
+        Ok(())
+    }
+
+    /// Returns up to `limit` reader feedback entries for a page via the ArticleFeedbackv5 extension
+    pub fn get_feedback_for_page(
+        &mut self,
+        title: &str,
+        limit: u32,
+    ) -> Result<Vec<FeedbackEntry>, Box<dyn ::std::error::Error>> {
+        self.require_article_feedback()?;
+        let limit = limit.to_string();
+        let params = hashmap!["action"=>"query","list"=>"articlefeedbackv5-view-feedback","afvftitle"=>title,"afvflimit"=>limit.as_str()];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        match result["query"]["articlefeedbackv5-view-feedback"]["feedback"].as_array() {
+            Some(feedback) => Ok(feedback.iter().map(FeedbackEntry::from_json).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Counts all entries of a `list=` query, following continuation
+    fn count_list_entries(
+        &mut self,
+        params: &HashMap<&str, &str>,
+        list: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let mut count = 0;
+        for entry in ContinuationIter::new(self, params, list) {
+            entry?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Returns the number of pages linking to `title`.
+    /// The API has no count, so all backlinks are fetched
+    pub fn get_pages_linking_here_count(
+        &mut self,
+        title: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"query","list"=>"backlinks","bltitle"=>title,"bllimit"=>"max"];
+        self.count_list_entries(&params, "backlinks")
+    }
+
+    /// Returns the number of pages transcluding `title`.
+    /// The API has no count, so all transclusions are fetched
+    pub fn get_transclusion_count(
+        &mut self,
+        title: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"query","list"=>"embeddedin","eititle"=>title,"eilimit"=>"max"];
+        self.count_list_entries(&params, "embeddedin")
+    }
+
+    /// Returns an iterator over the pages transcluding `template` (including the namespace prefix), via `list=embeddedin`,
+    /// optionally only in namespace `ns`, and optionally stopping after `limit` pages
+    pub fn get_pages_transcluding(
+        &mut self,
+        template: &str,
+        ns: Option<i32>,
+        limit: Option<u32>,
+    ) -> impl Iterator<Item = Result<PageInfo, Box<dyn ::std::error::Error>>> + '_ {
+        let ns = ns.map(|ns| ns.to_string());
+        let eilimit = match limit {
+            Some(limit) if limit < 500 => limit.to_string(),
+            _ => "max".to_string(),
+        };
+        let mut params = hashmap!["action"=>"query","list"=>"embeddedin","eititle"=>template,"eilimit"=>eilimit.as_str()];
+        if let Some(ns) = &ns {
+            params.insert("einamespace", ns);
+        }
+        ContinuationIter::new(self, &params, "embeddedin")
+            .map(|entry| entry.map(|e| PageInfo::from_json(&e)))
+            .take(limit.map(|l| l as usize).unwrap_or(usize::MAX))
+    }
+
+    /// Returns the number of pages that call `template` (including the namespace prefix) directly in their wikitext,
+    /// not only through another template. This loads the wikitext of every page transcluding the template;
+    /// calls through a redirect to the template are not counted
+    pub fn get_direct_template_count(
+        &mut self,
+        template: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let titles = self
+            .get_pages_transcluding(template, None, None)
+            .map(|page| Ok(page?.title))
+            .collect::<Result<Vec<String>, Box<dyn ::std::error::Error>>>()?;
+        let params = hashmap!["prop"=>"revisions","rvprop"=>"content","rvslots"=>"main"];
+        let pages = self.query_pages_batched(&titles, &params)?;
+        let prefixes = self.get_namespace_names(10);
+        Ok(pages
+            .iter()
+            .filter_map(|page| page["revisions"][0]["slots"]["main"]["*"].as_str())
+            .filter(|wikitext| {
+                Api::count_template_calls_with_prefixes(wikitext, template, &prefixes) > 0
+            })
+            .count() as u64)
+    }
+
+    /// Returns an iterator over the external links to `domain` (e.g. `example.com` or `*.example.com`), via `list=exturlusage`,
+    /// optionally stopping after `limit` links. Each entry has the `pageid`, `title`, and full `url`
+    pub fn get_all_page_links_to_external_domain(
+        &mut self,
+        domain: &str,
+        limit: Option<u32>,
+    ) -> ::std::iter::Take<ContinuationIter<'_>> {
+        let params = hashmap!["action"=>"query","list"=>"exturlusage","euquery"=>domain,"euprop"=>"ids|title|url","eulimit"=>"max"];
+        ContinuationIter::new(self, &params, "exturlusage")
+            .take(limit.map(|l| l as usize).unwrap_or(usize::MAX))
+    }
+
+    /// Returns the number of external links to `domain`.
+    /// The API has no count, so all links are fetched
+    pub fn get_external_link_count_for_domain(
+        &mut self,
+        domain: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"exturlusage","euquery"=>domain,"euprop"=>"ids","eulimit"=>"max"];
+        self.count_list_entries(&params, "exturlusage")
+    }
+
+    /// Returns the magic words of the wiki, as a map from the magic word name (e.g. `redirect`)
+    /// to its localized aliases
+    pub fn get_magic_words(
+        &mut self,
+    ) -> Result<HashMap<String, Vec<String>>, Box<dyn ::std::error::Error>> {
+        let mut ret = HashMap::new();
+        if let Some(magic_words) = self.get_site_info_property("magicwords")?.as_array() {
+            for mw in magic_words {
+                if let Some(name) = mw["name"].as_str() {
+                    ret.insert(name.to_string(), Api::json_strings(&mw["aliases"]));
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns all user groups of the wiki, with their rights
+    pub fn get_all_user_groups(
+        &mut self,
+    ) -> Result<Vec<UserGroupInfo>, Box<dyn ::std::error::Error>> {
+        match self.get_site_info_property("usergroups")?.as_array() {
+            Some(groups) => Ok(groups.iter().map(UserGroupInfo::from_json).collect()),
+            None => Err(From::from("No user groups in site info")),
+        }
+    }
+
+    /// Returns the rights of a user group
+    pub fn get_user_group_rights(
+        &mut self,
+        group: &str,
+    ) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        match self
+            .get_all_user_groups()?
+            .into_iter()
+            .find(|g| g.name == group)
+        {
+            Some(group) => Ok(group.rights),
+            None => Err(From::from(format!("No such user group: {}", group))),
+        }
+    }
+
+    /// Checks if a user group has a specific right.
+    /// Returns `false` if the group does not exist, or the user groups can not be loaded
+    pub fn user_group_has_right(&mut self, group: &str, right: &str) -> bool {
+        match self.get_user_group_rights(group) {
+            Ok(rights) => rights.iter().any(|r| r == right),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the `restrictions` site info
+    fn get_site_info_restrictions(&mut self) -> Result<Value, Box<dyn ::std::error::Error>> {
+        Ok(self.get_site_info_property("restrictions")?.clone())
+    }
+
+    /// Returns the actions that can be protected, e.g. `edit` and `move`
+    pub fn get_restriction_types(&mut self) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        Ok(Api::json_strings(
+            &self.get_site_info_restrictions()?["types"],
+        ))
+    }
+
+    /// Returns the protection expiry options offered by the wiki, e.g. `1 week` or `infinite`.
+    /// Site info has no such list, so this is parsed from the `protect-expiry-options` interface message
+    pub fn get_protection_expiry_options(
+        &mut self,
+    ) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","meta"=>"allmessages","ammessages"=>"protect-expiry-options","amlang"=>"en"];
+        let result = self.get_query_api_json(&params)?;
+        match result["query"]["allmessages"][0]["*"].as_str() {
+            Some(options) => Ok(options
+                .split(',')
+                .filter_map(|o| o.rsplit(':').next())
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect()),
+            None => Err(From::from("No protect-expiry-options message")),
+        }
+    }
+
+    /// Checks if `level` (e.g. `sysop`) is a protection level of the wiki
+    pub fn is_valid_protection_level(
+        &mut self,
+        level: &str,
+    ) -> Result<bool, Box<dyn ::std::error::Error>> {
+        let levels = Api::json_strings(&self.get_site_info_restrictions()?["levels"]);
+        Ok(levels.iter().any(|l| l == level))
+    }
+
+    /// Checks if `expiry` is a valid protection expiry:
+    /// `infinite` (or a synonym), a relative time like `2 weeks`, or an ISO 8601 timestamp
+    pub fn is_valid_protection_expiry(expiry: &str) -> bool {
+        let expiry = expiry.trim();
+        if ["infinite", "indefinite", "infinity", "never"].contains(&expiry) {
+            return true;
+        }
+        if chrono::DateTime::parse_from_rfc3339(expiry).is_ok() {
+            return true;
+        }
+        let parts: Vec<&str> = expiry.split_whitespace().collect();
+        if parts.len() != 2 || parts[0].parse::<u32>().is_err() {
+            return false;
+        }
+        let unit = parts[1].trim_end_matches('s');
+        ["second", "minute", "hour", "day", "week", "month", "year"].contains(&unit)
+    }
+
+    /// Returns the strings in a JSON array
+    fn json_strings(arr: &Value) -> Vec<String> {
+        match arr.as_array() {
+            Some(arr) => arr
+                .iter()
+                .filter_map(|x| x.as_str())
+                .map(|s| s.to_string())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Returns the redirect target of `wikitext`, if it starts with one of the `keywords` (e.g. `#REDIRECT`).
+    /// Any `#section` part of the target is removed
+    pub fn parse_redirect_target(wikitext: &str, keywords: &[String]) -> Option<String> {
+        let text = wikitext.trim_start();
+        let lower = text.to_lowercase();
+        let keyword = keywords
+            .iter()
+            .find(|k| lower.starts_with(&k.to_lowercase()))?;
+        let rest = text.get(keyword.len()..)?.trim_start();
+        let rest = rest.strip_prefix(':').unwrap_or(rest).trim_start();
+        let rest = rest.strip_prefix("[[")?;
+        let end = rest.find("]]")?;
+        let target = rest[..end].split('|').next()?;
+        let target = target.split('#').next()?.trim();
+        if target.is_empty() {
+            None
+        } else {
+            Some(target.to_string())
+        }
+    }
+
+    /// Returns the localized redirect keywords of the wiki, including `#REDIRECT`
+    fn get_redirect_keywords(&mut self) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        let mut keywords = self
+            .get_magic_words()?
+            .remove("redirect")
+            .unwrap_or_default();
+        if !keywords.iter().any(|k| k.eq_ignore_ascii_case("#REDIRECT")) {
+            keywords.push("#REDIRECT".to_string());
+        }
+        Ok(keywords)
+    }
+
+    /// Follows the redirects starting at `title`, based on the wikitext and the localized redirect keywords,
+    /// for up to 5 hops. Returns the final title
+    pub fn resolve_magic_redirect(
+        &mut self,
+        title: &str,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let keywords = self.get_redirect_keywords()?;
+        let mut current = title.to_string();
+        let mut seen = HashSet::new();
+        for _ in 0..5 {
+            seen.insert(current.clone());
+            let wikitext = match self.get_page_wikitext(&current)? {
+                Some(wikitext) => wikitext,
+                None => break,
+            };
+            match Api::parse_redirect_target(&wikitext, &keywords) {
+                Some(target) => {
+                    if seen.contains(&target) {
+                        break;
+                    }
+                    current = target;
+                }
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
+    /// Checks if `title` is a redirect to another redirect
+    pub fn is_double_redirect(
+        &mut self,
+        title: &str,
+    ) -> Result<bool, Box<dyn ::std::error::Error>> {
+        let keywords = self.get_redirect_keywords()?;
+        let target = match self.get_page_wikitext(title)? {
+            Some(wikitext) => Api::parse_redirect_target(&wikitext, &keywords),
+            None => None,
+        };
+        let target = match target {
+            Some(target) => target,
+            None => return Ok(false),
+        };
+        match self.get_page_wikitext(&target)? {
+            Some(wikitext) => Ok(Api::parse_redirect_target(&wikitext, &keywords).is_some()),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns all change tags defined or used on the wiki
+    pub fn get_change_tags_list(&mut self) -> Result<Vec<TagInfo>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"tags","tglimit"=>"max","tgprop"=>"displayname|description|hitcount|defined|active|source"];
+        let result = self.get_query_api_json_all(&params)?;
+        match result["query"]["tags"].as_array() {
+            Some(tags) => Ok(tags.iter().map(TagInfo::from_json).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Runs a `managetags` operation (`create`, `delete`, `activate`, `deactivate`) on a change tag
+    fn manage_change_tag(
+        &mut self,
+        operation: &str,
+        name: &str,
+        reason: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"managetags","operation"=>operation,"tag"=>name,"reason"=>reason];
+        self.post_with_edit_token(&params)?;
+        Ok(())
+    }
+
+    /// Creates a new change tag
+    pub fn create_change_tag(
+        &mut self,
+        name: &str,
+        reason: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        self.manage_change_tag("create", name, reason)
+    }
+
+    /// Deletes a change tag
+    pub fn delete_change_tag(
+        &mut self,
+        name: &str,
+        reason: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        self.manage_change_tag("delete", name, reason)
+    }
+
+    /// Returns the daily page views of a page over the last `days` days, as `(YYYYMMDD, views)` tuples,
+    /// via the Wikimedia REST API. The project (e.g. `en.wikipedia.org`) is taken from the site info
+    pub fn get_page_view_stats(
+        &mut self,
+        title: &str,
+        days: u32,
+    ) -> Result<Vec<(String, u64)>, Box<dyn ::std::error::Error>> {
+        let server = self.get_site_info_string("general", "server")?;
+        let project = server
+            .trim_start_matches("https:")
+            .trim_start_matches("http:")
+            .trim_start_matches("//");
+        let now = chrono::Utc::now();
+        let start = now - chrono::Duration::days(i64::from(days));
+        let url = format!(
+            "https://wikimedia.org/api/rest_v1/metrics/pageviews/per-article/{}/all-access/all-agents/{}/daily/{}/{}",
+            project,
+            urlencoding::encode(&title.replace(' ', "_")),
+            start.format("%Y%m%d"),
+            now.format("%Y%m%d")
+        );
+        let result = self.query_raw(&url, &HashMap::new(), "GET")?;
+        let result: Value = serde_json::from_str(&result)?;
+        match result["items"].as_array() {
+            Some(items) => Ok(items
+                .iter()
+                .filter_map(|item| {
+                    let date = item["timestamp"].as_str()?.get(0..8)?.to_string();
+                    Some((date, item["views"].as_u64()?))
+                })
+                .collect()),
+            None => Err(From::from(format!(
+                "No page view data for '{}' on {}",
+                title, project
+            ))),
+        }
+    }
+
+    /// Returns the total page views of a page over the last `days` days
+    pub fn get_total_page_views(
+        &mut self,
+        title: &str,
+        days: u32,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let stats = self.get_page_view_stats(title, days)?;
+        Ok(stats.iter().map(|(_, views)| views).sum())
+    }
+
+    /// Returns the date (`YYYYMMDD`) with the most page views of a page over the last `days` days
+    pub fn get_peak_view_date(
+        &mut self,
+        title: &str,
+        days: u32,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let stats = self.get_page_view_stats(title, days)?;
+        match stats.into_iter().max_by_key(|(_, views)| *views) {
+            Some((date, _)) => Ok(date),
+            None => Err(From::from(format!("No page views for '{}'", title))),
+        }
+    }
+
+    /// Returns the link graph around `seed_titles`, as a map from page title to the titles it links to.
+    /// Links are followed for up to `depth` hops; no more than `max_nodes` pages are visited
+    pub fn get_link_graph(
+        &mut self,
+        seed_titles: &[&str],
+        depth: u32,
+        max_nodes: usize,
+    ) -> Result<HashMap<String, Vec<String>>, Box<dyn ::std::error::Error>> {
+        let mut ret = HashMap::new();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut frontier = vec![];
+        for title in seed_titles.iter().take(max_nodes) {
+            if visited.insert(title.to_string()) {
+                frontier.push(title.to_string());
+            }
+        }
+        let params = hashmap!["prop"=>"links","pllimit"=>"max"];
+        for _ in 0..depth {
+            if frontier.is_empty() {
+                break;
+            }
+            let pages = self.query_pages_batched(&frontier, &params)?;
+            frontier.clear();
+            for page in pages {
+                let title = match page["title"].as_str() {
+                    Some(title) => title.to_string(),
+                    None => continue,
+                };
+                let links = Api::page_titles_in(&page, "links");
+                for link in &links {
+                    if visited.len() < max_nodes && visited.insert(link.clone()) {
+                        frontier.push(link.clone());
+                    }
+                }
+                ret.insert(title, links);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the language code and project family (e.g. `en` and `wiki`) of a Wikimedia wiki,
+    /// based on the `lang` and `wikiid` (e.g. `enwiki`) site info values
+    fn get_wiki_language_and_family(
+        &self,
+    ) -> Result<(String, String), Box<dyn ::std::error::Error>> {
+        let lang = self.get_site_info_string("general", "lang")?;
+        let wikiid = self.get_site_info_string("general", "wikiid")?;
+        match wikiid.strip_prefix(&lang.replace('-', "_")) {
+            Some(family) => Ok((lang, family.to_string())),
+            None => Err(From::from(format!(
+                "Can not determine project family of '{}'",
+                wikiid
+            ))),
+        }
+    }
+
+    /// Returns the translations of a page, as a map from language code to title.
+    /// Combines the language links of the page with the sitelinks of its Wikibase item;
+    /// the Wikibase sitelinks take precedence. The item is loaded from the Wikibase API set with `set_entity_import_config`,
+    /// or from Wikidata
+    pub fn get_page_translations(
+        &mut self,
+        title: &str,
+    ) -> Result<HashMap<String, String>, Box<dyn ::std::error::Error>> {
+        let (own_lang, family) = self.get_wiki_language_and_family()?;
+        let params = hashmap!["action"=>"query","prop"=>"langlinks|pageprops","titles"=>title,"lllimit"=>"max","ppprop"=>"wikibase_item"];
+        let result = self.get_query_api_json_all(&params)?;
+        let mut ret = HashMap::new();
+        let mut item = None;
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for page in pages.values() {
+                if let Some(langlinks) = page["langlinks"].as_array() {
+                    for ll in langlinks {
+                        if let (Some(lang), Some(t)) = (ll["lang"].as_str(), ll["*"].as_str()) {
+                            ret.insert(lang.to_string(), t.to_string());
+                        }
+                    }
+                }
+                if let Some(q) = page["pageprops"]["wikibase_item"].as_str() {
+                    item = Some(q.to_string());
+                }
+            }
+        }
+
+        let item = match item {
+            Some(item) => item,
+            None => return Ok(ret),
+        };
+        let params = hashmap!["action"=>"wbgetentities","ids"=>item.as_str(),"props"=>"sitelinks","format"=>"json"];
+        let wikibase_api_url = self.get_wikibase_api_url();
+        let result = self.query_raw(&wikibase_api_url, &params, "GET")?;
+        let result: Value = serde_json::from_str(&result)?;
+        Api::check_api_error(&result)?;
+        ret.extend(Api::sitelink_translations(
+            &result["entities"][&item]["sitelinks"],
+            &family,
+            &own_lang,
+        ));
+        Ok(ret)
+    }
+
+    /// Returns the titles of `sitelinks` (the `sitelinks` of an entity) on language wikis of `family`,
+    /// by language code, except `own_lang`
+    fn sitelink_translations(
+        sitelinks: &Value,
+        family: &str,
+        own_lang: &str,
+    ) -> HashMap<String, String> {
+        let mut ret = HashMap::new();
+        let non_language_wikis = [
+            "commons",
+            "meta",
+            "species",
+            "sources",
+            "wikidata",
+            "mediawiki",
+            "incubator",
+            "outreach",
+        ];
+        if let Some(sitelinks) = sitelinks.as_object() {
+            for (site, sitelink) in sitelinks {
+                let lang = match site.strip_suffix(family) {
+                    Some(lang) => lang.replace('_', "-"),
+                    None => continue,
+                };
+                if lang.is_empty()
+                    || lang == own_lang
+                    || non_language_wikis.contains(&lang.as_str())
+                {
+                    continue;
+                }
+                if let Some(t) = sitelink["title"].as_str() {
+                    ret.insert(lang, t.to_string());
+                }
+            }
+        }
+        ret
+    }
+
+    /// Returns the fraction of active (not closed) language wikis of the same project family
+    /// that have a translation of the page, based on `action=sitematrix`
+    pub fn get_translation_coverage(
+        &mut self,
+        title: &str,
+    ) -> Result<f64, Box<dyn ::std::error::Error>> {
+        let (_, family) = self.get_wiki_language_and_family()?;
+        let params = hashmap!["action"=>"sitematrix","smtype"=>"language","smlangprop"=>"code|site","smsiteprop"=>"code","smlimit"=>"max"];
+        let result = self.get_query_api_json(&params)?;
+        let mut total = 0;
+        if let Some(languages) = result["sitematrix"].as_object() {
+            for language in languages.values() {
+                if let Some(sites) = language["site"].as_array() {
+                    if sites.iter().any(|site| {
+                        site["code"].as_str() == Some(family.as_str()) && site["closed"].is_null()
+                    }) {
+                        total += 1;
+                    }
+                }
+            }
+        }
+        if total == 0 {
+            return Err(From::from(format!("No active '{}' wikis found", family)));
+        }
+        // The page itself counts as one of the existing translations
+        let translations = self.get_page_translations(title)?.len() + 1;
+        Ok(translations as f64 / f64::from(total))
+    }
+
+    /// Returns the raw `prop=references` data of a page.
+    /// Only available where the Cite extension stores reference data
+    fn get_page_references_json(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<Value>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","prop"=>"references","titles"=>title];
+        let result = self.get_query_api_json_all(&params)?;
+        Api::check_api_error(&result)?;
+        let mut ret = vec![];
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for page in pages.values() {
+                if let Some(references) = page["references"].as_array() {
+                    ret.extend(references.iter().cloned());
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the references on a page, in order, via the Cite extension's `prop=references`
+    pub fn get_cite_extension_info(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<Reference>, Box<dyn ::std::error::Error>> {
+        let references = self.get_page_references_json(title)?;
+        Ok(references
+            .iter()
+            .enumerate()
+            .map(|(num, r)| Reference {
+                name: r["key"].as_str().map(|s| s.to_string()),
+                content: r["text"].as_str().unwrap_or("").to_string(),
+                ref_index: num as u32 + 1,
+            })
+            .collect())
+    }
+
+    /// Returns the number of references on a page
+    pub fn count_references(&mut self, title: &str) -> Result<u64, Box<dyn ::std::error::Error>> {
+        Ok(self.get_page_references_json(title)?.len() as u64)
+    }
+
+    /// Returns the number of files per MIME type, most common first.
+    /// All files are fetched via `list=allimages` and counted locally;
+    /// `limit` restricts the number of files examined
+    pub fn get_all_file_types(
+        &mut self,
+        limit: Option<u32>,
+    ) -> Result<Vec<(String, u64)>, Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"query","list"=>"allimages","aiprop"=>"mime","ailimit"=>"max"];
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        let files = ContinuationIter::new(self, &params, "allimages");
+        let files: Box<dyn Iterator<Item = _>> = match limit {
+            Some(limit) => Box::new(files.take(limit as usize)),
+            None => Box::new(files),
+        };
+        for file in files {
+            let file = file?;
+            let mime = file["mime"].as_str().unwrap_or("").to_string();
+            *counts.entry(mime).or_insert(0) += 1;
+        }
+        let mut ret: Vec<(String, u64)> = counts.into_iter().collect();
+        ret.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Ok(ret)
+    }
+
+    /// Returns an iterator over all files of a MIME type, fetched `limit` at a time.
+    /// Note that `aimime` is disabled on wikis running in miser mode, like Wikimedia wikis
+    pub fn get_files_by_mime(&mut self, mime: &str, limit: u32) -> ContinuationIter<'_> {
+        let limit = limit.to_string();
+        let params = hashmap!["action"=>"query","list"=>"allimages","aimime"=>mime,"ailimit"=>limit.as_str(),"aiprop"=>"mime|url|size"];
+        ContinuationIter::new(self, &params, "allimages")
+    }
+
+    /// Returns up to `limit` results of a special page via `list=querypage`, e.g. `Lonelypages`.
+    /// Each result has `ns`, `title`, and usually a `value`.
+    /// Most of these special pages are pre-computed by the job queue, so the data may be hours or days old
+    pub fn get_query_page(
+        &mut self,
+        qppage: &str,
+        limit: u32,
+    ) -> Result<Vec<Value>, Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"query","list"=>"querypage","qppage"=>qppage,"qplimit"=>"max"];
+        ContinuationIter::new_with_path(self, &params, &["query", "querypage", "results"])
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Returns up to `limit` results of a special page as `PageInfo`; page IDs are not available
+    fn get_query_page_info(
+        &mut self,
+        qppage: &str,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        let results = self.get_query_page(qppage, limit)?;
+        Ok(results.iter().map(PageInfo::from_json).collect())
+    }
+
+    /// Returns up to `limit` results of a special page as `PageInfo`, with the `value` of each result (usually a count),
+    /// optionally only those in namespace `ns`; page IDs are not available
+    fn get_query_page_counts(
+        &mut self,
+        qppage: &str,
+        limit: u32,
+        ns: Option<i32>,
+    ) -> Result<Vec<(PageInfo, u64)>, Box<dyn ::std::error::Error>> {
+        let params =
+            hashmap!["action"=>"query","list"=>"querypage","qppage"=>qppage,"qplimit"=>"max"];
+        let mut ret = vec![];
+        if limit == 0 {
+            return Ok(ret);
+        }
+        for result in
+            ContinuationIter::new_with_path(self, &params, &["query", "querypage", "results"])
+        {
+            let result = result?;
+            let page = PageInfo::from_json(&result);
+            if ns.is_some_and(|ns| ns != page.ns) {
+                continue;
+            }
+            let count = match &result["value"] {
+                Value::String(v) => v.parse().unwrap_or(0),
+                v => v.as_u64().unwrap_or(0),
+            };
+            ret.push((page, count));
+            if ret.len() >= limit as usize {
+                break;
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns pages that do not exist but are linked to (`Special:WantedPages`), with the number of links,
+    /// optionally only in namespace `ns`. The data is pre-computed, and may be hours old
+    pub fn get_all_missing_pages(
+        &mut self,
+        limit: u32,
+        ns: Option<i32>,
+    ) -> Result<Vec<(PageInfo, u64)>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_counts("Wantedpages", limit, ns)
+    }
+
+    /// Returns templates that do not exist but are used (`Special:WantedTemplates`), with the number of uses.
+    /// The data is pre-computed, and may be hours old
+    pub fn get_wanted_templates(
+        &mut self,
+        limit: u32,
+    ) -> Result<Vec<(PageInfo, u64)>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_counts("Wantedtemplates", limit, None)
+    }
+
+    /// Returns categories that do not exist but have members (`Special:WantedCategories`), with the number of members.
+    /// The data is pre-computed, and may be hours old
+    pub fn get_wanted_categories(
+        &mut self,
+        limit: u32,
+    ) -> Result<Vec<(PageInfo, u64)>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_counts("Wantedcategories", limit, None)
+    }
+
+    /// Returns files that do not exist but are used (`Special:WantedFiles`), with the number of uses.
+    /// The data is pre-computed, and may be hours old
+    pub fn get_wanted_files(
+        &mut self,
+        limit: u32,
+    ) -> Result<Vec<(PageInfo, u64)>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_counts("Wantedfiles", limit, None)
+    }
+
+    /// Returns files that are not used on any page (`Special:UnusedFiles`).
+    /// The data is pre-computed, and may be hours old
+    pub fn get_orphaned_images(
+        &mut self,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_info("Unusedimages", limit)
+    }
+
+    /// Returns articles that are not linked from any other page (`Special:LonelyPages`).
+    /// The data is pre-computed, and may be hours old
+    pub fn get_orphaned_articles(
+        &mut self,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_info("Lonelypages", limit)
+    }
+
+    /// Returns categories that contain no pages (`Special:UnusedCategories`).
+    /// The data is pre-computed, and may be hours old
+    pub fn get_orphaned_categories(
+        &mut self,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        self.get_query_page_info("Unusedcategories", limit)
+    }
+
+    /// Returns the sections of a page, via `action=parse`
+    pub fn get_section_list(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<ParseSection>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"parse","page"=>title,"prop"=>"sections"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        match result["parse"]["sections"].as_array() {
+            Some(sections) => Ok(sections.iter().map(ParseSection::from_json).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the wikitext of a single section of a page (0 being the lead section),
+    /// or `None` if the page does not exist
+    pub fn get_wikitext_section(
+        &mut self,
+        title: &str,
+        section: u32,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        let section = section.to_string();
+        let params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvprop"=>"content","rvslots"=>"main","rvsection"=>section.as_str()];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        Ok(Api::first_revision_content(&result))
+    }
+
+    /// Returns the wikitext of a section of a page, an edit token, and the ID of the current revision,
+    /// in a single API call. The revision ID can be used as `baserevid` to detect edit conflicts
+    pub fn get_page_section_wikitext_edit_token(
+        &mut self,
+        title: &str,
+        section: u32,
+    ) -> Result<(String, String, u64), Box<dyn ::std::error::Error>> {
+        let section = section.to_string();
+        let params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvprop"=>"content|ids","rvslots"=>"main","rvsection"=>section.as_str(),"meta"=>"tokens","type"=>"csrf"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let wikitext = match Api::first_revision_content(&result) {
+            Some(wikitext) => wikitext,
+            None => return Err(From::from(format!("No section {} in '{}'", section, title))),
+        };
+        let token = match result["query"]["tokens"]["csrftoken"].as_str() {
+            Some(token) => token.to_string(),
+            None => return Err(From::from("Could not get token")),
+        };
+        let rev_id = result["query"]["pages"].as_object().and_then(|pages| {
+            pages
+                .values()
+                .filter_map(|p| p["revisions"][0]["revid"].as_u64())
+                .next()
+        });
+        match rev_id {
+            Some(rev_id) => Ok((wikitext, token, rev_id)),
+            None => Err(From::from(format!("No revision ID for '{}'", title))),
+        }
+    }
+
+    /// Splits the wikitext of a page by the byte offsets of its sections.
+    /// The lead section (index `0`, level 0) is added first; sections without a byte offset are skipped
+    fn split_wikitext_sections(
+        sections: Vec<ParseSection>,
+        wikitext: &str,
+    ) -> Vec<(ParseSection, String)> {
+        let local: Vec<(ParseSection, usize)> = sections
+            .into_iter()
+            .filter_map(|s| s.byteoffset.map(|o| (s, o)))
+            .collect();
+        let lead_end = local.first().map(|(_, o)| *o).unwrap_or(wikitext.len());
+        let lead = ParseSection {
+            toclevel: 0,
+            level: 0,
+            line: "".to_string(),
+            number: "".to_string(),
+            index: "0".to_string(),
+            byteoffset: Some(0),
+            anchor: "".to_string(),
+        };
+        let mut ret = vec![];
+        if let Some(text) = wikitext.get(..lead_end) {
+            ret.push((lead, text.to_string()));
+        }
+        for (num, (section, start)) in local.iter().enumerate() {
+            let end = local[num + 1..]
+                .iter()
+                .find(|(s, _)| s.level <= section.level)
+                .map(|(_, o)| *o)
+                .unwrap_or(wikitext.len());
+            if let Some(text) = wikitext.get(*start..end) {
+                ret.push((section.clone(), text.to_string()));
+            }
+        }
+        ret
+    }
+
+    /// Returns the wikitext of each section of a page, including its subsections,
+    /// starting with the lead section before the first heading (index `0`, level 0).
+    /// The sections and the page wikitext are loaded together, from the same revision, and the wikitext is split by the section byte offsets.
+    /// Sections transcluded from templates are not part of the page wikitext, so they are not returned
+    pub fn get_page_wikitext_sections(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<(ParseSection, String)>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"parse","page"=>title,"prop"=>"sections|wikitext"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let wikitext = match result["parse"]["wikitext"]["*"].as_str() {
+            Some(wikitext) => wikitext,
+            None => return Err(From::from(format!("No wikitext for {}", title))),
+        };
+        let sections = match result["parse"]["sections"].as_array() {
+            Some(sections) => sections.iter().map(ParseSection::from_json).collect(),
+            None => vec![],
+        };
+        Ok(Api::split_wikitext_sections(sections, wikitext))
+    }
+
+    /// Returns the discussions (level 2 sections) on the talk page of `title`,
+    /// with the user and time of the last signature in each
+    pub fn get_all_talk_page_discussions(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<TalkSection>, Box<dyn ::std::error::Error>> {
+        let talk_title = format!("Talk:{}", title);
+        Ok(self
+            .get_page_wikitext_sections(&talk_title)?
+            .iter()
+            .filter(|(section, _)| section.level == 2)
+            .filter_map(|(section, text)| {
+                let index = section.index.parse().ok()?;
+                Some(TalkSection::from_wikitext(&section.line, index, text))
+            })
+            .collect())
+    }
+
+    /// Moves a section of the talk page `title` to the end of `archive_title`, creating the archive page if necessary,
+    /// then removes the section from `title`. The removal is based on the revision the section was read from,
+    /// so MediaWiki reports an edit conflict if the page was changed in between
+    pub fn archive_talk_section(
+        &mut self,
+        title: &str,
+        section_index: u32,
+        archive_title: &str,
+        summary: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        if section_index == 0 {
+            return Err(From::from("Can not archive the lead section"));
+        }
+        let (text, token, rev_id) =
+            self.get_page_section_wikitext_edit_token(title, section_index)?;
+        self.append_to_page(archive_title, &format!("\n\n{}", text.trim()), summary)?;
+        let section = section_index.to_string();
+        let rev_id = rev_id.to_string();
+        let params = hashmap!["action"=>"edit","title"=>title,"section"=>section.as_str(),"text"=>"","summary"=>summary,"nocreate"=>"1","baserevid"=>rev_id.as_str(),"token"=>token.as_str()];
+        let result = self.post_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        Ok(())
+    }
+
+    /// Checks if the last signature in a discussion is at least `min_age_days` days old.
+    /// Discussions without a recognized signature are never archived
+    pub fn should_archive(section: &TalkSection, min_age_days: u32) -> bool {
+        match &section.last_timestamp {
+            Some(timestamp) => timestamp.as_str() <= Api::timestamp_days_ago(min_age_days).as_str(),
+            None => false,
+        }
+    }
+
+    /// Returns the number of lint errors per category, via `meta=linterstats` of the Linter extension
+    pub fn get_linter_category_counts(
+        &mut self,
+    ) -> Result<HashMap<String, u64>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","meta"=>"linterstats"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        match result["query"]["linterstats"]["totals"].as_object() {
+            Some(totals) => Ok(totals
+                .iter()
+                .filter_map(|(category, count)| Some((category.to_string(), count.as_u64()?)))
+                .collect()),
+            None => Err(From::from("No linter stats in result")),
+        }
+    }
+
+    /// Returns the priority (e.g. `high`, `medium`, or `low`) of a lint error category,
+    /// from the `linter` categories by priority in the general site info.
+    /// Wikis that do not list them there fall back to the defaults of the Linter extension,
+    /// after a `list=linterrors` query checks that the category name is valid on this wiki.
+    /// Categories without a known priority are an `Err`
+    pub fn get_linter_category_priority(
+        &mut self,
+        category: &str,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let linter = &self.site_info["query"]["general"]["linter"];
+        if linter.is_object() {
+            return match Api::linter_priority_from_site_info(linter, category) {
+                Some(priority) => Ok(priority),
+                None => Err(From::from(format!("Unknown lint category {}", category))),
+            };
+        }
+        let params = hashmap!["action"=>"query","list"=>"linterrors","lntcategories"=>category,"lntlimit"=>"1"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let priority = match category {
+            "deletable-table-tag"
+            | "html5-misnesting"
+            | "misc-tidy-replacement-issues"
+            | "multiline-html-table-in-list"
+            | "multiple-unclosed-formatting-tags"
+            | "pwrap-bug-workaround"
+            | "self-closed-tag"
+            | "tidy-font-bug"
+            | "tidy-whitespace-bug"
+            | "unclosed-quotes-in-heading" => "high",
+            "bogus-image-options"
+            | "fostered"
+            | "misnested-tag"
+            | "multi-colon-escape"
+            | "wikilink-in-extlink" => "medium",
+            "large-tables"
+            | "night-mode-unaware-background-color"
+            | "missing-end-tag"
+            | "missing-end-tag-in-heading"
+            | "obsolete-tag"
+            | "stripped-tag" => "low",
+            _ => {
+                return Err(From::from(format!(
+                    "No known priority for lint category {}",
+                    category
+                )))
+            }
+        };
+        Ok(priority.to_string())
+    }
+
+    /// Returns the priority of `category` in the `linter` site info, which lists the categories by priority
+    fn linter_priority_from_site_info(linter: &Value, category: &str) -> Option<String> {
+        linter
+            .as_object()?
+            .iter()
+            .find(|(_, categories)| {
+                categories
+                    .as_array()
+                    .map(|c| c.iter().any(|c| c.as_str() == Some(category)))
+                    .unwrap_or(false)
+            })
+            .map(|(priority, _)| priority.to_string())
+    }
+
+    /// Returns the targets listed on a disambiguation page: the first link of every list item (`*` or `#` line)
+    pub fn parse_disambiguation_targets(wikitext: &str) -> Vec<String> {
+        wikitext
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| line.starts_with('*') || line.starts_with('#'))
+            .filter_map(|line| {
+                let start = line.find("[[")? + 2;
+                let end = start + line[start..].find("]]")?;
+                let target = line[start..end].split('|').next()?.trim();
+                if target.is_empty() {
+                    None
+                } else {
+                    Some(target.to_string())
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the targets listed on a disambiguation page, or `None` if `title` is not a disambiguation page
+    /// (according to the `disambiguation` page property of the Disambiguator extension)
+    pub fn get_page_disambiguation_targets(
+        &mut self,
+        title: &str,
+    ) -> Result<Option<Vec<String>>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","prop"=>"pageprops","ppprop"=>"disambiguation","titles"=>title];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let is_disambiguation = result["query"]["pages"]
+            .as_object()
+            .map(|pages| {
+                pages
+                    .values()
+                    .any(|page| !page["pageprops"]["disambiguation"].is_null())
+            })
+            .unwrap_or(false);
+        if !is_disambiguation {
+            return Ok(None);
+        }
+        let wikitext = self.get_page_wikitext(title)?.unwrap_or_default();
+        Ok(Some(Api::parse_disambiguation_targets(&wikitext)))
+    }
+
+    /// Returns an iterator over up to `limit` disambiguation pages in a category (including the namespace prefix).
+    /// Each entry has the `pageid`, `ns`, and `title` of the page
+    pub fn get_disambiguation_pages_in_category(
+        &mut self,
+        category: &str,
+        limit: u32,
+    ) -> impl Iterator<Item = Result<Value, Box<dyn ::std::error::Error>>> + '_ {
+        let params = hashmap!["action"=>"query","generator"=>"categorymembers","gcmtitle"=>category,"gcmlimit"=>"max","prop"=>"pageprops","ppprop"=>"disambiguation","formatversion"=>"2"];
+        ContinuationIter::new(self, &params, "pages")
+            .filter(|page| match page {
+                Ok(page) => !page["pageprops"]["disambiguation"].is_null(),
+                Err(_) => true,
+            })
+            .take(limit as usize)
+    }
+
+    /// Returns up to `limit` pages matching a `list=search` query, optionally in namespace `ns`
+    fn search_pages(
+        &mut self,
+        srsearch: &str,
+        srwhat: &str,
+        ns: Option<i32>,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        let ns = ns.map(|ns| ns.to_string());
+        let mut params = hashmap!["action"=>"query","list"=>"search","srsearch"=>srsearch,"srwhat"=>srwhat,"srprop"=>"","srlimit"=>"max"];
+        if let Some(ns) = &ns {
+            params.insert("srnamespace", ns);
+        }
+        ContinuationIter::new(self, &params, "search")
+            .take(limit as usize)
+            .map(|r| r.map(|r| PageInfo::from_json(&r)))
+            .collect()
+    }
+
+    /// Returns up to `limit` pages whose wikitext contains `pattern`, via full-text search
+    pub fn find_pages_with_wikitext(
+        &mut self,
+        pattern: &str,
+        ns: Option<i32>,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        self.search_pages(pattern, "text", ns, limit)
+    }
+
+    /// Returns up to `limit` pages whose wikitext matches the regular expression `regex`,
+    /// via an `insource:/<regex>/` search
+    pub fn find_pages_with_insource(
+        &mut self,
+        regex: &str,
+        ns: Option<i32>,
+        limit: u32,
+    ) -> Result<Vec<PageInfo>, Box<dyn ::std::error::Error>> {
+        let srsearch = format!("insource:/{}/", regex);
+        self.search_pages(&srsearch, "text", ns, limit)
+    }
+
+    /// Returns all templates transcluded on a page, directly or indirectly.
+    /// Combines `generator=templates` with the template list of `action=parse`; both tell if the template exists
+    pub fn get_templates_in_page(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<TemplateUsage>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"parse","page"=>title,"prop"=>"templates"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let mut ret: Vec<TemplateUsage> = vec![];
+        if let Some(templates) = result["parse"]["templates"].as_array() {
+            for t in templates {
+                if let Some(template_title) = t["*"].as_str() {
+                    ret.push(TemplateUsage {
+                        title: template_title.to_string(),
+                        ns: t["ns"].as_i64().unwrap_or(10) as i32,
+                        exists: !t["exists"].is_null(),
+                    });
+                }
+            }
+        }
+
+        let params =
+            hashmap!["action"=>"query","generator"=>"templates","titles"=>title,"gtllimit"=>"max"];
+        let result = self.get_query_api_json_all(&params)?;
+        Api::check_api_error(&result)?;
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for t in pages.values() {
+                let template_title = t["title"].as_str().unwrap_or("");
+                if template_title.is_empty() || ret.iter().any(|u| u.title == template_title) {
+                    continue;
+                }
+                ret.push(TemplateUsage {
+                    title: template_title.to_string(),
+                    ns: t["ns"].as_i64().unwrap_or(10) as i32,
+                    exists: t["missing"].is_null(),
+                });
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Counts the direct calls of `template` (with or without `Template:` prefix) in `wikitext`.
+    /// The first letter is case-insensitive, and spaces and underscores are treated the same
+    pub fn count_template_calls(wikitext: &str, template: &str) -> u64 {
+        Api::count_template_calls_with_prefixes(wikitext, template, &["Template".to_string()])
+    }
+
+    /// Same as `count_template_calls`, but with any of `prefixes` (case-insensitive) as the template namespace name,
+    /// e.g. the result of `get_namespace_names(10)` for localized names
+    pub fn count_template_calls_with_prefixes(
+        wikitext: &str,
+        template: &str,
+        prefixes: &[String],
+    ) -> u64 {
+        let prefixes: Vec<String> = prefixes
+            .iter()
+            .map(|p| p.replace('_', " ").to_lowercase())
+            .collect();
+        let strip_prefix = |name: &str| -> String {
+            let name = name.trim().replace('_', " ");
+            match name.find(':') {
+                Some(pos) if prefixes.contains(&name[..pos].trim().to_lowercase()) => {
+                    name[pos + 1..].trim().to_string()
+                }
+                _ => name,
+            }
+        };
+        let template = strip_prefix(template);
+        let mut chars = template.chars();
+        let (first, rest) = match chars.next() {
+            Some(first) => (first, chars.as_str()),
+            None => return 0,
+        };
+        wikitext
+            .split("{{")
+            .skip(1)
+            .filter(|call| {
+                let name = strip_prefix(call.split(['|', '}']).next().unwrap_or(""));
+                let mut name_chars = name.chars();
+                match name_chars.next() {
+                    Some(c) => {
+                        c.to_lowercase().eq(first.to_lowercase()) && name_chars.as_str() == rest
+                    }
+                    None => false,
+                }
+            })
+            .count() as u64
+    }
+
+    /// Returns the local and canonical names, and the aliases, of namespace `ns` from the site info
+    pub fn get_namespace_names(&self, ns: i32) -> Vec<String> {
+        let namespace = &self.site_info["query"]["namespaces"][ns.to_string()];
+        let mut ret: Vec<String> = vec![&namespace["*"], &namespace["canonical"]]
+            .into_iter()
+            .filter_map(|name| name.as_str())
+            .map(|name| name.to_string())
+            .collect();
+        if let Some(aliases) = self.site_info["query"]["namespacealiases"].as_array() {
+            ret.extend(
+                aliases
+                    .iter()
+                    .filter(|alias| alias["id"].as_i64() == Some(ns as i64))
+                    .filter_map(|alias| alias["*"].as_str())
+                    .map(|alias| alias.to_string()),
+            );
+        }
+        ret.retain(|name| !name.is_empty());
+        ret.dedup();
+        ret
+    }
+
+    /// Returns the number of direct calls of `template` in the wikitext of a page
+    pub fn count_template_uses_on_page(
+        &mut self,
+        title: &str,
+        template: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        match self.get_page_wikitext(title)? {
+            Some(wikitext) => Ok(Api::count_template_calls_with_prefixes(
+                &wikitext,
+                template,
+                &self.get_namespace_names(10),
+            )),
+            None => Err(From::from(format!("No such page: {}", title))),
+        }
+    }
+
+    /// Returns the average number of edits per day by `user` over the last `days` days
+    pub fn get_user_contribs_speed(
+        &mut self,
+        user: &str,
+        days: u32,
+    ) -> Result<f64, Box<dyn ::std::error::Error>> {
+        if days == 0 {
+            return Err(From::from("days must be larger than 0"));
+        }
+        let ucend = Api::timestamp_days_ago(days);
+        let params = hashmap!["action"=>"query","list"=>"usercontribs","ucuser"=>user,"ucend"=>ucend.as_str(),"uclimit"=>"max","ucprop"=>"ids"];
+        let count = self.count_list_entries(&params, "usercontribs")?;
+        Ok(count as f64 / f64::from(days))
+    }
+
+    /// Returns the `list=users` result object for a single user, with the given `usprop`
+    fn get_user_info(
+        &mut self,
+        user: &str,
+        usprop: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"users","ususers"=>user,"usprop"=>usprop];
+        let result = self.get_query_api_json(&params)?;
+        let info = &result["query"]["users"][0];
+        if info.is_null() || !info["missing"].is_null() || !info["invalid"].is_null() {
+            return Err(From::from(format!("No such user: {}", user)));
+        }
+        Ok(info.clone())
+    }
+
+    /// Returns the registration timestamp of a user.
+    /// `None` for very old accounts, which have no registration date
+    pub fn get_user_registration_date(
+        &mut self,
+        user: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        let info = self.get_user_info(user, "registration")?;
+        Ok(info["registration"].as_str().map(|s| s.to_string()))
+    }
+
+    /// Returns the registration date, edit count, and recent edit rate of a user
+    pub fn get_user_activity_profile(
+        &mut self,
+        user: &str,
+    ) -> Result<UserActivityProfile, Box<dyn ::std::error::Error>> {
+        let info = self.get_user_info(user, "registration|editcount")?;
+        Ok(UserActivityProfile {
+            user: info["name"].as_str().unwrap_or(user).to_string(),
+            registration: info["registration"].as_str().map(|s| s.to_string()),
+            edit_count: info["editcount"].as_u64().unwrap_or(0),
+            edits_per_day_last_30_days: self.get_user_contribs_speed(user, 30)?,
+        })
+    }
+
+    /// Returns the activity of the wiki for the last `months` months (including the current one), oldest first.
+    /// New articles and users come from the page creation and new user logs.
+    /// Edits come from recent changes, which only go back about 30 days, so older months have no edits.
+    /// Every entry is fetched and counted, so this is slow on large wikis
+    pub fn get_wiki_growth_stats(
+        &mut self,
+        months: u32,
+    ) -> Result<Vec<GrowthStats>, Box<dyn ::std::error::Error>> {
+        use chrono::Datelike;
+        let today = chrono::Utc::now().date_naive();
+        let mut year = today.year();
+        let mut month = today.month();
+        let mut ret = vec![];
+        for _ in 0..months {
+            let start = format!("{:04}-{:02}-01T00:00:00Z", year, month);
+            let (next_year, next_month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+            let end = format!("{:04}-{:02}-01T00:00:00Z", next_year, next_month);
+
+            let params = hashmap!["action"=>"query","list"=>"logevents","letype"=>"create","lenamespace"=>"0","lestart"=>end.as_str(),"leend"=>start.as_str(),"lelimit"=>"max","leprop"=>"ids"];
+            let new_articles = self.count_list_entries(&params, "logevents")?;
+            let params = hashmap!["action"=>"query","list"=>"logevents","letype"=>"newusers","lestart"=>end.as_str(),"leend"=>start.as_str(),"lelimit"=>"max","leprop"=>"ids"];
+            let new_users = self.count_list_entries(&params, "logevents")?;
+            let params = hashmap!["action"=>"query","list"=>"recentchanges","rctype"=>"edit|new","rcstart"=>end.as_str(),"rcend"=>start.as_str(),"rclimit"=>"max","rcprop"=>"ids"];
+            let total_edits = self.count_list_entries(&params, "recentchanges")?;
+
+            ret.push(GrowthStats {
+                year_month: format!("{:04}-{:02}", year, month),
+                new_articles,
+                total_edits,
+                new_users,
+            });
+            if month == 1 {
+                year -= 1;
+                month = 12;
+            } else {
+                month -= 1;
+            }
+        }
+        ret.reverse();
+        Ok(ret)
+    }
+
+    /// Returns a rollback token for the edits of `user` on a page.
+    /// Fails if the current revision of the page is not by `user`, as there would be nothing to roll back.
+    /// Uses the page-specific `rvtoken=rollback` where the wiki still supports it, and `meta=tokens` otherwise
+    pub fn get_rollback_token(
+        &mut self,
+        title: &str,
+        user: &str,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvprop"=>"ids|user","rvtoken"=>"rollback","meta"=>"tokens","type"=>"rollback"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let revision = result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().map(|p| p["revisions"][0].clone()).next())
+            .unwrap_or(Value::Null);
+        if revision["user"].as_str() != Some(user) {
+            return Err(From::from(format!(
+                "The current revision of '{}' is not by {}",
+                title, user
+            )));
+        }
+        match revision["rollbacktoken"]
+            .as_str()
+            .or_else(|| result["query"]["tokens"]["rollbacktoken"].as_str())
+        {
+            Some(token) => Ok(token.to_string()),
+            None => Err(From::from("Could not get token")),
+        }
+    }
+
+    /// Rolls back the most recent edits of `user` on a page
+    pub fn rollback(
+        &mut self,
+        title: &str,
+        user: &str,
+        summary: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let token = self.get_rollback_token(title, user)?;
+        let params = hashmap!["action"=>"rollback","title"=>title,"user"=>user,"summary"=>summary,"token"=>token.as_str()];
+        let result = self.post_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        Ok(result)
+    }
+
+    /// Returns the pages where the current revision is by `user`, with that revision;
+    /// these are the edits that can be rolled back
+    pub fn get_all_rollback_candidates(
+        &mut self,
+        user: &str,
+    ) -> Result<Vec<(String, Revision)>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","list"=>"usercontribs","ucuser"=>user,"ucshow"=>"top","uclimit"=>"max","ucprop"=>"ids|title|timestamp|comment|size|flags"];
+        let result = self.get_query_api_json_all(&params)?;
+        let mut ret = vec![];
+        if let Some(contribs) = result["query"]["usercontribs"].as_array() {
+            for c in contribs {
+                if let Some(title) = c["title"].as_str() {
+                    ret.push((title.to_string(), Revision::from_json(c)));
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the direct redirect target of a page, based on its wikitext,
+    /// or `None` if the page is not a redirect or does not exist
+    pub fn get_redirect_target(
+        &mut self,
+        title: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        let keywords = self.get_redirect_keywords()?;
+        match self.get_page_wikitext(title)? {
+            Some(wikitext) => Ok(Api::parse_redirect_target(&wikitext, &keywords)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the file name without the (possibly localized) namespace prefix
+    fn strip_namespace_prefix(title: &str) -> String {
+        match title.find(':') {
+            Some(pos) => title[pos + 1..].to_string(),
+            None => title.to_string(),
+        }
+    }
+
+    /// Returns the file that a file redirect points to, or `None` if `filename` is not a redirect.
+    /// File names are without the `File:` prefix
+    pub fn get_file_redirect_target(
+        &mut self,
+        filename: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        let title = format!("File:{}", filename);
+        let params =
+            hashmap!["action"=>"query","prop"=>"info","titles"=>title.as_str(),"redirects"=>"1"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        Ok(result["query"]["redirects"]
+            .as_array()
+            .and_then(|redirects| redirects.last())
+            .and_then(|redirect| redirect["to"].as_str())
+            .map(Api::strip_namespace_prefix))
+    }
+
+    /// Returns all file redirects to `target`, via `list=backlinks`.
+    /// File names are without the `File:` prefix
+    pub fn get_all_file_redirects_to(
+        &mut self,
+        target: &str,
+    ) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        let title = format!("File:{}", target);
+        let params = hashmap!["action"=>"query","list"=>"backlinks","bltitle"=>title.as_str(),"blfilterredir"=>"redirects","bllimit"=>"max"];
+        let mut ret = vec![];
+        for backlink in ContinuationIter::new(self, &params, "backlinks") {
+            if let Some(title) = backlink?["title"].as_str() {
+                ret.push(Api::strip_namespace_prefix(title));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Follows the redirects from `seed_titles`, and returns each redirect loop found, as the titles in the loop.
+    /// Uses Brent's cycle detection; each loop is returned once, starting with its alphabetically first title
+    pub fn detect_redirect_loops(
+        &mut self,
+        seed_titles: &[&str],
+    ) -> Result<Vec<Vec<String>>, Box<dyn ::std::error::Error>> {
+        let mut targets: HashMap<String, Option<String>> = HashMap::new();
+        let mut ret: Vec<Vec<String>> = vec![];
+        for seed in seed_titles {
+            // Brent's algorithm: `power` is the current search window, `lam` the distance from `tortoise` to `hare`
+            let mut power = 1;
+            let mut lam = 1;
+            let mut tortoise = seed.to_string();
+            let mut hare = match self.get_cached_redirect_target(&mut targets, &tortoise)? {
+                Some(hare) => hare,
+                None => continue,
+            };
+            let mut has_loop = true;
+            while tortoise != hare {
+                if power == lam {
+                    tortoise = hare.clone();
+                    power *= 2;
+                    lam = 0;
+                }
+                hare = match self.get_cached_redirect_target(&mut targets, &hare)? {
+                    Some(next) => next,
+                    None => {
+                        has_loop = false;
+                        break;
+                    }
+                };
+                lam += 1;
+            }
+            if !has_loop {
+                continue;
+            }
+
+            // `hare` is in the loop, which has length `lam`
+            let mut cycle = vec![hare.clone()];
+            let mut current = hare;
+            for _ in 1..lam {
+                current = match targets.get(&current) {
+                    Some(Some(next)) => next.clone(),
+                    _ => break,
+                };
+                cycle.push(current.clone());
+            }
+            if let Some(min_pos) = (0..cycle.len()).min_by_key(|&i| &cycle[i]) {
+                cycle.rotate_left(min_pos);
+            }
+            if !ret.contains(&cycle) {
+                ret.push(cycle);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the redirect target of `title`, using and filling the `targets` cache
+    fn get_cached_redirect_target(
+        &mut self,
+        targets: &mut HashMap<String, Option<String>>,
+        title: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        if let Some(target) = targets.get(title) {
+            return Ok(target.clone());
+        }
+        let target = self.get_redirect_target(title)?;
+        targets.insert(title.to_string(), target.clone());
+        Ok(target)
+    }
+
+    /// Changes each redirect in `loop_titles` to point directly to `final_target`.
+    /// `final_target` must be an existing page outside the loop, and not a redirect itself
+    pub fn repair_redirect_loop(
+        &mut self,
+        loop_titles: &[&str],
+        final_target: &str,
+        summary: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        if loop_titles.contains(&final_target) {
+            return Err(From::from(format!(
+                "{} is part of the redirect loop",
+                final_target
+            )));
+        }
+        let keywords = self.get_redirect_keywords()?;
+        match self.get_page_wikitext(final_target)? {
+            None => return Err(From::from(format!("{} does not exist", final_target))),
+            Some(wikitext) => {
+                if Api::parse_redirect_target(&wikitext, &keywords).is_some() {
+                    return Err(From::from(format!("{} is a redirect", final_target)));
+                }
+            }
+        }
+        let text = format!("#REDIRECT [[{}]]", final_target);
+        for title in loop_titles {
+            self.edit_page(title, &text, summary)?;
+        }
+        Ok(())
+    }
+
+    /// Performs a login against the MediaWiki API.
+    /// If successful, user information is stored in `MWuser`, and in the cookie jar
+    pub fn login(
+        &mut self,
+        lgname: &str,
+        lgpassword: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        let lgtoken = self.get_token("login")?;
+        let params = hashmap!("action"=>"login","lgname"=>&lgname,"lgpassword"=>&lgpassword,"lgtoken"=>&lgtoken);
+        let res = self.post_query_api_json(&params)?;
+        if res["login"]["result"] == "Success" {
+            self.user.set_from_login(&res["login"])?;
+            let params = hashmap!["action"=>"query","meta"=>"userinfo","uiprop"=>"rights"];
+            let res = self.get_query_api_json(&params)?;
+            self.user
+                .set_rights_from_userinfo(&res["query"]["userinfo"]);
+            Ok(())
+        } else {
+            panic!("Login failed") // TODO proper error return
+        }
+    }
+
+    /// Checks if the logged-in user has a specific right, e.g. `deletedtext`
+    pub fn user_has_right(&self, right: &str) -> bool {
+        self.user.has_right(right)
+    }
+
+    /// Returns the wikitext of a deleted revision of a page.
+    /// Requires the `deletedtext` right
+    pub fn get_deleted_page_content(
+        &mut self,
+        title: &str,
+        rev_id: u64,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        if !self.user_has_right("deletedtext") {
+            return Err(From::from("User does not have the 'deletedtext' right"));
+        }
+        let rev_id = rev_id.to_string();
+        let params = hashmap!["action"=>"query","prop"=>"deletedrevisions","revids"=>rev_id.as_str(),"drvprop"=>"ids|content","drvslots"=>"main"];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let title = title.replace('_', " ");
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for page in pages.values() {
+                if page["title"].as_str() != Some(title.as_str()) {
+                    continue;
+                }
+                if let Some(text) = page["deletedrevisions"][0]["slots"]["main"]["*"].as_str() {
+                    return Ok(text.to_string());
+                }
+            }
+        }
+        Err(From::from(format!(
+            "No deleted revision {} for '{}'",
+            rev_id, title
+        )))
+    }
+
+    /// Returns all deleted revisions of a page, without their content
+    pub fn list_deleted_page_revisions(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<Revision>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"query","prop"=>"deletedrevisions","titles"=>title,"drvlimit"=>"max","drvprop"=>"ids|timestamp|user|comment|size|flags"];
+        let result = self.get_query_api_json_all(&params)?;
+        Api::check_api_error(&result)?;
+        let mut ret = vec![];
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for page in pages.values() {
+                if let Some(revisions) = page["deletedrevisions"].as_array() {
+                    ret.extend(revisions.iter().map(Revision::from_json));
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the IDs of all suppressed revisions of a page.
+    /// Requires the `viewsuppressed` right
+    pub fn get_suppressed_revisions(
+        &mut self,
+        title: &str,
+    ) -> Result<Vec<u64>, Box<dyn ::std::error::Error>> {
+        if !self.user_has_right("viewsuppressed") {
+            return Err(From::from("User does not have the 'viewsuppressed' right"));
+        }
+        let params = hashmap!["action"=>"query","prop"=>"revisions","titles"=>title,"rvlimit"=>"max","rvprop"=>"ids|sha1"];
+        let result = self.get_query_api_json_all(&params)?;
+        let mut ret = vec![];
+        if let Some(pages) = result["query"]["pages"].as_object() {
+            for page in pages.values() {
+                if let Some(revisions) = page["revisions"].as_array() {
+                    ret.extend(
+                        revisions
+                            .iter()
+                            .filter(|r| !r["suppressed"].is_null())
+                            .filter_map(|r| r["revid"].as_u64()),
+                    );
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Lifts the suppression from the content, comment, and user of a revision.
+    /// Requires the `suppressrevision` right
+    pub fn unsuppress_revision(
+        &mut self,
+        rev_id: u64,
+        reason: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        if !self.user_has_right("suppressrevision") {
+            return Err(From::from(
+                "User does not have the 'suppressrevision' right",
+            ));
+        }
+        let rev_id = rev_id.to_string();
+        let params = hashmap!["action"=>"revisiondelete","type"=>"revision","ids"=>rev_id.as_str(),"show"=>"content|comment|user","suppress"=>"no","reason"=>reason];
+        self.post_with_edit_token(&params)?;
+        Ok(())
+    }
+
+    /// Returns the JSON of a single entity, limited to the given `props` (e.g. `sitelinks|claims`)
+    fn wikibase_get_entity_props(
+        &mut self,
+        entity_id: &str,
+        props: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"wbgetentities","ids"=>entity_id,"props"=>props];
+        let result = self.get_query_api_json(&params)?;
+        let entity = &result["entities"][entity_id];
+        if entity.is_null() || entity["missing"].is_string() {
+            return Err(From::from(format!("No such entity: {}", entity_id)));
+        }
+        Ok(entity.clone())
+    }
+
+    /// Returns the data type of a property, e.g. `wikibase-item` or `external-id`
+    pub fn get_wikibase_property_type(
+        &mut self,
+        property_id: &str,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let entity = self.wikibase_get_entity_props(property_id, "datatype")?;
+        match entity["datatype"].as_str() {
+            Some(datatype) => Ok(datatype.to_string()),
+            None => Err(From::from(format!("{} has no datatype", property_id))),
+        }
+    }
+
+    /// Returns the type of an entity, e.g. `item`, `property` or `lexeme`, without loading the entity data
+    pub fn wikibase_get_entity_type(
+        &mut self,
+        id: &str,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let entity = self.wikibase_get_entity_props(id, "info")?;
+        match entity["type"].as_str() {
+            Some(entity_type) => Ok(entity_type.to_string()),
+            None => Err(From::from(format!("{} has no entity type", id))),
+        }
+    }
+
+    /// Checks if `id` is a well-formed entity ID (`Q1`, `P1`, `L1`, `L1-F1`, or `L1-S1`), without an API call
+    pub fn is_valid_entity_id(id: &str) -> bool {
+        let is_number =
+            |s: &str| !s.is_empty() && !s.starts_with('0') && s.chars().all(|c| c.is_ascii_digit());
+        let mut parts = id.splitn(2, '-');
+        let main = parts.next().unwrap_or("");
+        if !main.is_char_boundary(1) || !is_number(&main[1..]) {
+            return false;
+        }
+        match (&main[..1], parts.next()) {
+            ("Q", None) | ("P", None) | ("L", None) => true,
+            ("L", Some(sub)) => {
+                (sub.starts_with('F') || sub.starts_with('S')) && is_number(&sub[1..])
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the URL of the latest full JSON dump of all Wikidata entities.
+    /// Only works on Wikidata
+    pub fn get_linked_open_data_export_url(&self) -> Result<String, Box<dyn ::std::error::Error>> {
+        if !self.is_wikidata() {
+            return Err(From::from("Entity dumps are only available for Wikidata"));
+        }
+        Ok("https://dumps.wikimedia.org/wikidatawiki/entities/latest-all.json.gz".to_string())
+    }
+
+    /// Sets the source and templates used by `import_wikidata_entity_to_wiki`
+    pub fn set_entity_import_config(&mut self, config: EntityImportConfig) {
+        self.entity_import_config = Some(config);
+    }
+
+    /// Returns the URL of the Wikibase API set with `set_entity_import_config`, or the Wikidata API
+    fn get_wikibase_api_url(&self) -> String {
+        match &self.entity_import_config {
+            Some(config) => config.wikibase_api_url.to_string(),
+            None => "https://www.wikidata.org/w/api.php".to_string(),
+        }
+    }
+
+    /// Returns a data value as plain wikitext
+    fn data_value_to_wikitext(value: &DataValue) -> String {
+        match value {
+            DataValue::EntityId(id) => id.to_string(),
+            DataValue::String(s) => s.to_string(),
+            DataValue::Quantity { amount, .. } => amount.to_string(),
+            DataValue::Time { value, .. } => value.to_string(),
+            DataValue::Coordinate { lat, lon } => format!("{},{}", lat, lon),
+            DataValue::Monolingual { text, .. } => text.to_string(),
+        }
+    }
+
+    /// Returns the page wikitext for an entity: one template call for the `lang` label and description,
+    /// then one per non-deprecated claim value, by property ID
+    fn entity_import_wikitext(entity: &Value, config: &EntityImportConfig, lang: &str) -> String {
+        let call = |template: &str, value: &str| format!("{{{{{}|{}}}}}", template, value);
+        let mut lines = vec![];
+        if let (Some(template), Some(label)) = (
+            &config.label_template,
+            entity["labels"][lang]["value"].as_str(),
+        ) {
+            lines.push(call(template, label));
+        }
+        if let (Some(template), Some(description)) = (
+            &config.description_template,
+            entity["descriptions"][lang]["value"].as_str(),
+        ) {
+            lines.push(call(template, description));
+        }
+        let mut properties: Vec<&String> = config.property_templates.keys().collect();
+        properties.sort();
+        for property in properties {
+            let template = &config.property_templates[property];
+            if let Some(claims) = entity["claims"][property].as_array() {
+                lines.extend(
+                    claims
+                        .iter()
+                        .filter_map(Claim::from_json)
+                        .filter(|c| c.rank != ClaimRank::Deprecated)
+                        .filter_map(|c| DataValue::from_json(&c.mainsnak["datavalue"]))
+                        .map(|v| call(template, &Api::data_value_to_wikitext(&v))),
+                );
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Creates or updates a page for a Wikibase entity in namespace `target_ns` of this wiki, titled by the entity ID.
+    /// The entity is loaded from the Wikibase API, and converted to template calls, as set with `set_entity_import_config`;
+    /// labels and descriptions are in the content language of this wiki. Returns the title of the page
+    pub fn import_wikidata_entity_to_wiki(
+        &mut self,
+        entity_id: &str,
+        target_ns: i32,
+    ) -> Result<String, Box<dyn ::std::error::Error>> {
+        let config = match &self.entity_import_config {
+            Some(config) => config.clone(),
+            None => return Err(From::from("No entity import config set")),
+        };
+        let mut wikibase = Api::new_with_client(&config.wikibase_api_url, self.client.clone())?;
+        let entity = wikibase.wikibase_get_entity_props(entity_id, "labels|descriptions|claims")?;
+        let lang = self.get_site_info_string("general", "lang")?;
+        let wikitext = Api::entity_import_wikitext(&entity, &config, &lang);
+
+        let title = match self.site_info["query"]["namespaces"][target_ns.to_string()]["*"].as_str()
+        {
+            Some("") => entity_id.to_string(),
+            Some(ns_name) => format!("{}:{}", ns_name, entity_id),
+            None => return Err(From::from(format!("No such namespace: {}", target_ns))),
+        };
+        let summary = format!("Import of Wikibase entity {}", entity_id);
+        self.edit_page(&title, &wikitext, &summary)?;
+        Ok(title)
+    }
+
+    /// Returns the page, JSON and RDF URLs of an entity, via `Special:EntityPage` and `Special:EntityData`
+    pub fn get_entity_dump_url(&self, entity_id: &str) -> EntityUrls {
+        let base = format!(
+            "{}index.php?title=Special:",
+            self.api_url.trim_end_matches("api.php")
+        );
+        EntityUrls {
+            page: format!("{}EntityPage/{}", base, entity_id),
+            json: format!("{}EntityData/{}.json", base, entity_id),
+            rdf: format!("{}EntityData/{}.ttl", base, entity_id),
+        }
+    }
+
+    /// Returns the labels of entities in the given languages, as a map from entity ID to language code to label.
+    /// Entities and languages are requested in batches of 50; a missing label is an empty string
+    pub fn wikibase_normalize_labels(
+        &mut self,
+        entity_ids: &[&str],
+        langs: &[&str],
+    ) -> Result<HashMap<String, HashMap<String, String>>, Box<dyn ::std::error::Error>> {
+        let mut ret: HashMap<String, HashMap<String, String>> = entity_ids
+            .iter()
+            .map(|id| {
+                let labels = langs
+                    .iter()
+                    .map(|lang| (lang.to_string(), "".to_string()))
+                    .collect();
+                (id.to_string(), labels)
+            })
+            .collect();
+        for ids in entity_ids.chunks(50) {
+            let ids = ids.join("|");
+            for languages in langs.chunks(50) {
+                let languages = languages.join("|");
+                let params = hashmap!["action"=>"wbgetentities","ids"=>ids.as_str(),"props"=>"labels","languages"=>languages.as_str()];
+                let result = self.get_query_api_json(&params)?;
+                Api::check_api_error(&result)?;
+                let entities = match result["entities"].as_object() {
+                    Some(entities) => entities,
+                    None => continue,
+                };
+                for (id, entity) in entities {
+                    let (entry, labels) = match (ret.get_mut(id), entity["labels"].as_object()) {
+                        (Some(entry), Some(labels)) => (entry, labels),
+                        _ => continue,
+                    };
+                    for (lang, label) in labels {
+                        if let Some(text) = label["value"].as_str() {
+                            entry.insert(lang.to_string(), text.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the number of sitelinks of an entity, without loading the rest of the entity
+    pub fn wikibase_get_entity_sitelink_count(
+        &mut self,
+        entity_id: &str,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let entity = self.wikibase_get_entity_props(entity_id, "sitelinks")?;
+        match entity["sitelinks"].as_object() {
+            Some(sitelinks) => Ok(sitelinks.len() as u64),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the number of claims of an entity, without loading the rest of the entity.
+    /// If `property` is given, only claims for that property are counted
+    pub fn wikibase_get_entity_claim_count(
+        &mut self,
+        entity_id: &str,
+        property: Option<&str>,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let entity = self.wikibase_get_entity_props(entity_id, "claims")?;
+        let claims = match entity["claims"].as_object() {
+            Some(claims) => claims,
+            None => return Ok(0),
+        };
+        let count = claims
+            .iter()
+            .filter(|(p, _)| match property {
+                Some(property) => property == p.as_str(),
+                None => true,
+            })
+            .filter_map(|(_, c)| c.as_array())
+            .map(|c| c.len() as u64)
+            .sum();
+        Ok(count)
+    }
+
+    /// Returns the claims of an entity for a single property, via `wbgetclaims`
+    pub fn get_wikibase_statements_for_property(
+        &mut self,
+        entity_id: &str,
+        property_id: &str,
+    ) -> Result<Vec<Claim>, Box<dyn ::std::error::Error>> {
+        let params = hashmap!["action"=>"wbgetclaims","entity"=>entity_id,"property"=>property_id];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        match result["claims"][property_id].as_array() {
+            Some(claims) => Ok(claims.iter().filter_map(Claim::from_json).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the "best" claims of an entity for a property:
+    /// the preferred-rank claims, or the normal-rank claims if there are no preferred ones
+    pub fn get_best_claims(
+        &mut self,
+        entity_id: &str,
+        property_id: &str,
+    ) -> Result<Vec<Claim>, Box<dyn ::std::error::Error>> {
+        let claims = self.get_wikibase_statements_for_property(entity_id, property_id)?;
+        let rank = if claims.iter().any(|c| c.rank == ClaimRank::Preferred) {
+            ClaimRank::Preferred
+        } else {
+            ClaimRank::Normal
+        };
+        Ok(claims.into_iter().filter(|c| c.rank == rank).collect())
+    }
+
+    /// Returns the data values of the claims of an entity for a property, optionally only for claims of `rank`.
+    /// Claims without a value (`novalue`/`somevalue`) are skipped
+    pub fn get_entity_data_value(
+        &mut self,
+        entity_id: &str,
+        property_id: &str,
+        rank: Option<ClaimRank>,
+    ) -> Result<Vec<DataValue>, Box<dyn ::std::error::Error>> {
+        let claims = self.get_wikibase_statements_for_property(entity_id, property_id)?;
+        Ok(claims
+            .iter()
+            .filter(|c| match rank {
+                Some(rank) => c.rank == rank,
+                None => true,
+            })
+            .filter_map(|c| DataValue::from_json(&c.mainsnak["datavalue"]))
+            .collect())
+    }
+
+    /// Returns the first entity ID value of the best claims of an entity for a property
+    fn get_best_entity_value(
+        &mut self,
+        entity_id: &str,
+        property_id: &str,
+    ) -> Result<Option<EntityId>, Box<dyn ::std::error::Error>> {
+        let claims = self.get_best_claims(entity_id, property_id)?;
+        Ok(claims
+            .iter()
+            .filter_map(|c| match DataValue::from_json(&c.mainsnak["datavalue"]) {
+                Some(DataValue::EntityId(id)) => Some(id),
+                _ => None,
+            })
+            .next())
+    }
+
+    /// Returns the class path of an item, starting with the item itself, then its `P31` (instance of) class,
+    /// then following `P279` (subclass of) for up to `max_hops` steps in total, or until a root class or a loop is reached
+    pub fn get_wikidata_ontology_path(
+        &mut self,
+        item_id: &str,
+        max_hops: u32,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        let mut path = vec![EntityId::new(item_id)];
+        let mut property = "P31";
+        for _ in 0..max_hops {
+            let current = path[path.len() - 1].as_str().to_string();
+            let next = match self.get_best_entity_value(&current, property)? {
+                Some(next) => next,
+                None => break,
+            };
+            if path.contains(&next) {
+                break;
+            }
+            path.push(next);
+            property = "P279";
+        }
+        Ok(path)
+    }
+
+    /// Returns the lowest class on the ontology path of `entity_a` that is also on the ontology path of `entity_b`,
+    /// or `None` if they share no class within `max_hops`
+    pub fn shares_class(
+        &mut self,
+        entity_a: &str,
+        entity_b: &str,
+        max_hops: u32,
+    ) -> Result<Option<EntityId>, Box<dyn ::std::error::Error>> {
+        let path_a = self.get_wikidata_ontology_path(entity_a, max_hops)?;
+        let path_b = self.get_wikidata_ontology_path(entity_b, max_hops)?;
+        Ok(path_a
+            .into_iter()
+            .skip(1)
+            .find(|class| path_b[1..].contains(class)))
+    }
+
+    /// Checks if this `Api` is for Wikidata, via the wiki ID (`wikidatawiki`) in the site info
+    pub fn is_wikidata(&self) -> bool {
+        self.site_info["query"]["general"]["wikiid"].as_str() == Some("wikidatawiki")
+    }
+
+    /// Returns the property constraint violations of the main snaks of an entity, via `wbcheckconstraints`.
+    /// Only works on Wikidata
+    pub fn get_property_constraint_violations(
+        &mut self,
+        entity_id: &str,
+    ) -> Result<Vec<ConstraintViolation>, Box<dyn ::std::error::Error>> {
+        if !self.is_wikidata() {
+            return Err(From::from(
+                "wbcheckconstraints is only available on Wikidata",
+            ));
+        }
+        let params = hashmap!["action"=>"wbcheckconstraints","id"=>entity_id];
+        let result = self.get_query_api_json(&params)?;
+        Api::check_api_error(&result)?;
+        let mut ret = vec![];
+        if let Some(claims) = result["wbcheckconstraints"][entity_id]["claims"].as_object() {
+            for statements in claims.values() {
+                for statement in statements.as_array().unwrap_or(&vec![]) {
+                    for r in statement["mainsnak"]["results"]
+                        .as_array()
+                        .unwrap_or(&vec![])
+                    {
+                        let violation = ConstraintViolation::from_json(r);
+                        if violation.status != "compliant" {
+                            ret.push(violation);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Runs a batch of QuickStatements v2 commands, one per line.
+    /// A failed command does not stop the batch; the result of each non-empty line is reported
+    pub fn wikibase_quick_statements_import(
+        &mut self,
+        qs_batch: &str,
+    ) -> Result<Vec<QsResult>, Box<dyn ::std::error::Error>> {
+        let mut last = None;
+        let mut ret = vec![];
+        for (num, line) in qs_batch.lines().enumerate() {
+            let line_number = num as u32 + 1;
+            let command = match quickstatements::parse_line(line) {
+                Ok(Some(command)) => command,
+                Ok(None) => continue,
+                Err(e) => {
+                    ret.push(QsResult {
+                        line: line_number,
+                        action: "".to_string(),
+                        success: false,
+                        error: Some(From::from(e)),
+                    });
+                    continue;
+                }
+            };
+            let error = self.run_quick_statements_command(&command, &mut last).err();
+            ret.push(QsResult {
+                line: line_number,
+                action: command.action().to_string(),
+                success: error.is_none(),
+                error,
+            });
+        }
+        Ok(ret)
+    }
+
+    /// Runs a single QuickStatements command.
+    /// `last` is the ID of the most recently created entity, used for `LAST`
+    fn run_quick_statements_command(
+        &mut self,
+        command: &QsCommand,
+        last: &mut Option<String>,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        let resolve = |entity: &str| -> Result<String, Box<dyn ::std::error::Error>> {
+            match (entity, last.as_ref()) {
+                ("LAST", Some(last)) => Ok(last.to_string()),
+                ("LAST", None) => Err(From::from("LAST used before CREATE")),
+                (entity, _) => Ok(entity.to_string()),
+            }
+        };
+        match command {
+            QsCommand::Create => {
+                let params = hashmap!["action"=>"wbeditentity","new"=>"item","data"=>"{}"];
+                let result = self.post_with_edit_token(&params)?;
+                match result["entity"]["id"].as_str() {
+                    Some(id) => *last = Some(id.to_string()),
+                    None => return Err(From::from("No entity ID for created item")),
+                }
+            }
+            QsCommand::Label {
+                entity,
+                language,
+                text,
+            } => {
+                let id = resolve(entity)?;
+                let params = hashmap!["action"=>"wbsetlabel","id"=>id.as_str(),"language"=>language.as_str(),"value"=>text.as_str()];
+                self.post_with_edit_token(&params)?;
+            }
+            QsCommand::Description {
+                entity,
+                language,
+                text,
+            } => {
+                let id = resolve(entity)?;
+                let params = hashmap!["action"=>"wbsetdescription","id"=>id.as_str(),"language"=>language.as_str(),"value"=>text.as_str()];
+                self.post_with_edit_token(&params)?;
+            }
+            QsCommand::Alias {
+                entity,
+                language,
+                text,
+            } => {
+                let id = resolve(entity)?;
+                let params = hashmap!["action"=>"wbsetaliases","id"=>id.as_str(),"language"=>language.as_str(),"add"=>text.as_str()];
+                self.post_with_edit_token(&params)?;
+            }
+            QsCommand::Sitelink {
+                entity,
+                site,
+                title,
+            } => {
+                let id = resolve(entity)?;
+                let params = hashmap!["action"=>"wbsetsitelink","id"=>id.as_str(),"linksite"=>site.as_str(),"linktitle"=>title.as_str()];
+                self.post_with_edit_token(&params)?;
+            }
+            QsCommand::Claim {
+                entity,
+                property,
+                value,
+                qualifiers,
+                references,
+            } => {
+                let id = resolve(entity)?;
+                let v = value.value.to_string();
+                let mut params = hashmap!["action"=>"wbcreateclaim","entity"=>id.as_str(),"property"=>property.as_str(),"snaktype"=>value.snaktype.as_str()];
+                if value.snaktype == "value" {
+                    params.insert("value", &v);
+                }
+                let result = self.post_with_edit_token(&params)?;
+                let claim_id = match result["claim"]["id"].as_str() {
+                    Some(claim_id) => claim_id.to_string(),
+                    None => return Err(From::from("No claim ID for created claim")),
+                };
+                for (qualifier_property, qualifier_value) in qualifiers {
+                    let v = qualifier_value.value.to_string();
+                    let mut params = hashmap!["action"=>"wbsetqualifier","claim"=>claim_id.as_str(),"property"=>qualifier_property.as_str(),"snaktype"=>qualifier_value.snaktype.as_str()];
+                    if qualifier_value.snaktype == "value" {
+                        params.insert("value", &v);
+                    }
+                    self.post_with_edit_token(&params)?;
+                }
+                if !references.is_empty() {
+                    let mut snaks = serde_json::Map::new();
+                    for (reference_property, reference_value) in references {
+                        let snak = reference_value.to_snak(reference_property);
+                        match snaks
+                            .entry(reference_property.to_string())
+                            .or_insert_with(|| Value::Array(vec![]))
+                        {
+                            Value::Array(arr) => arr.push(snak),
+                            _ => unreachable!(),
+                        }
+                    }
+                    let snaks = Value::Object(snaks).to_string();
+                    let params = hashmap!["action"=>"wbsetreference","statement"=>claim_id.as_str(),"snaks"=>snaks.as_str()];
+                    self.post_with_edit_token(&params)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if two snaks have the same type, property, and value
+    fn snaks_are_equal(a: &Value, b: &Value) -> bool {
+        a["snaktype"] == b["snaktype"]
+            && a["property"] == b["property"]
+            && a["datavalue"] == b["datavalue"]
+    }
+
+    /// Copies the claims for `property_id` from `source_entity` to `target_entity`, with their qualifiers and references.
+    /// Claims whose main snak already exists in the target are skipped.
+    /// If `remove_from_source` is set, all claims for `property_id` are then removed from the source.
+    /// Returns the number of claims added to the target
+    pub fn wikibase_merge_claims(
+        &mut self,
+        source_entity: &str,
+        target_entity: &str,
+        property_id: &str,
+        summary: &str,
+        remove_from_source: bool,
+    ) -> Result<u64, Box<dyn ::std::error::Error>> {
+        let source_claims =
+            self.get_wikibase_statements_for_property(source_entity, property_id)?;
+        let target_claims =
+            self.get_wikibase_statements_for_property(target_entity, property_id)?;
+        let new_claims: Vec<Value> = source_claims
+            .iter()
+            .filter(|c| !target_claims.iter().any(|t| Api::snaks_are_equal(&c.mainsnak, &t.mainsnak)))
+            .map(|c| {
+                let mut claim = serde_json::json!({"mainsnak":c.mainsnak,"type":"statement","rank":c.rank.as_str()});
+                if !c.qualifiers.is_null() {
+                    claim["qualifiers"] = c.qualifiers.clone();
+                }
+                if !c.references.is_null() {
+                    claim["references"] = c.references.clone();
+                }
+                claim
+            })
+            .collect();
+
+        if !new_claims.is_empty() {
+            let data = serde_json::json!({ "claims": new_claims }).to_string();
+            let params = hashmap!["action"=>"wbeditentity","id"=>target_entity,"data"=>data.as_str(),"summary"=>summary];
+            self.post_with_edit_token(&params)?;
+        }
+
+        if remove_from_source && !source_claims.is_empty() {
+            let ids: Vec<&str> = source_claims.iter().map(|c| c.id.as_str()).collect();
+            let ids = ids.join("|");
+            let params =
+                hashmap!["action"=>"wbremoveclaims","claim"=>ids.as_str(),"summary"=>summary];
+            self.post_with_edit_token(&params)?;
+        }
+        Ok(new_claims.len() as u64)
+    }
+
+    /// Adds several claims to an entity in a single `wbeditentity` edit.
+    /// Returns the IDs of the new claims, in the order of `claims`; these are the last claims
+    /// of each property in the result, as Wikibase appends new claims
+    pub fn wikibase_add_multiple_claims(
+        &mut self,
+        entity_id: &str,
+        claims: Vec<ClaimData>,
+        summary: &str,
+    ) -> Result<Vec<String>, Box<dyn ::std::error::Error>> {
+        if claims.is_empty() {
+            return Ok(vec![]);
+        }
+        let json_claims: Vec<Value> = claims.iter().map(|c| c.to_json()).collect();
+        let data = serde_json::json!({ "claims": json_claims }).to_string();
+        let params = hashmap!["action"=>"wbeditentity","id"=>entity_id,"data"=>data.as_str(),"summary"=>summary];
+        let result = self.post_with_edit_token(&params)?;
+
+        let mut new_per_property: HashMap<&str, usize> = HashMap::new();
+        for claim in &claims {
+            *new_per_property
+                .entry(claim.snak.property.as_str())
+                .or_insert(0) += 1;
+        }
+        let mut ret = vec![];
+        let mut seen_per_property: HashMap<&str, usize> = HashMap::new();
+        for claim in &claims {
+            let property = claim.snak.property.as_str();
+            let seen = seen_per_property.entry(property).or_insert(0);
+            let result_claims = result["entity"]["claims"][property]
+                .as_array()
+                .map(|a| a.as_slice())
+                .unwrap_or(&[]);
+            let first_new = result_claims
+                .len()
+                .saturating_sub(new_per_property[property]);
+            if let Some(id) = result_claims
+                .get(first_new + *seen)
+                .and_then(|c| c["id"].as_str())
+            {
+                ret.push(id.to_string());
+            }
+            *seen += 1;
+        }
+        Ok(ret)
+    }
+
+    /// Returns the page data (categories, wikitext), file metadata (`imageinfo`, including EXIF data),
+    /// and the structured data (MediaInfo entity, `Null` if the file has none, or the wiki has no WikibaseMediaInfo) of a file.
+    /// `filename` is without the `File:` prefix
+    pub fn get_all_structured_data_for_file(
+        &mut self,
+        filename: &str,
+    ) -> Result<FullFileInfo, Box<dyn ::std::error::Error>> {
+        let title = format!("File:{}", filename);
+        let params = hashmap!["action"=>"query","titles"=>title.as_str(),"prop"=>"imageinfo|categories|revisions","iiprop"=>"url|size|mime|extmetadata|metadata","cllimit"=>"max","rvprop"=>"content","rvslots"=>"main"];
+        let result = self.get_query_api_json_all(&params)?;
+        Api::check_api_error(&result)?;
+        let page = match result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+        {
+            Some(page) => page.clone(),
+            None => return Err(From::from(format!("No such file: {}", filename))),
+        };
+        let page_id = match page["pageid"].as_u64() {
+            Some(page_id) => page_id,
+            None => return Err(From::from(format!("No such file: {}", filename))),
+        };
+
+        let mediainfo_id = format!("M{}", page_id);
+        let mediainfo = if !self.is_extension_installed("WikibaseMediaInfo") {
+            Value::Null
+        } else {
+            let params = hashmap!["action"=>"wbgetentities","ids"=>mediainfo_id.as_str(),"props"=>"info|labels|descriptions|claims"];
+            let result = self.get_query_api_json(&params)?;
+            if result["error"]["code"].as_str() == Some("no-such-entity") {
+                Value::Null
+            } else {
+                Api::check_api_error(&result)?;
+                match &result["entities"][&mediainfo_id] {
+                    entity if entity["missing"].is_string() => Value::Null,
+                    entity => entity.clone(),
+                }
+            }
+        };
+
+        Ok(FullFileInfo {
+            title: page["title"].as_str().unwrap_or(&title).to_string(),
+            page_id,
+            categories: Api::page_titles_in(&page, "categories"),
+            wikitext: page["revisions"][0]["slots"]["main"]["*"]
+                .as_str()
+                .map(|s| s.to_string()),
+            imageinfo: page["imageinfo"][0].clone(),
+            mediainfo,
+        })
+    }
+
+    /// Returns the constraints of a Wikidata property, from its `P2302` (property constraint) claims
+    pub fn get_wikidata_constraints(
+        &mut self,
+        property_id: &str,
+    ) -> Result<Vec<PropertyConstraint>, Box<dyn ::std::error::Error>> {
+        let claims = self.get_wikibase_statements_for_property(property_id, "P2302")?;
+        Ok(claims
+            .iter()
+            .filter_map(PropertyConstraint::from_claim)
+            .collect())
+    }
+
+    /// Performs a SPARQL query against a wikibase installation.
+    /// Tries to get the SPARQL endpoint URL from the site info
+    pub fn sparql_query(&mut self, query: &str) -> Result<Value, Box<dyn ::std::error::Error>> {
+        let query_api_url = self.get_site_info_string("general", "wikibase-sparql")?;
+        let params = hashmap!["query"=>query,"format"=>"json"];
+        let result = self.query_raw(&query_api_url, &params, "GET")?;
+        Ok(serde_json::from_str(&result)?)
+    }
+
+    /// Performs a SPARQL query and returns the result bindings
+    fn sparql_bindings(&mut self, query: &str) -> Result<Vec<Value>, Box<dyn ::std::error::Error>> {
+        let result = self.sparql_query(query)?;
+        match result["results"]["bindings"].as_array() {
+            Some(bindings) => Ok(bindings.to_vec()),
+            None => Err(From::from("No bindings in SPARQL result")),
+        }
+    }
+
+    /// Returns an `Err` unless `id` is a well-formed entity ID, so it can be used in a SPARQL query
+    fn check_sparql_entity_id(id: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+        if Api::is_valid_entity_id(id) {
+            Ok(())
+        } else {
+            Err(From::from(format!("Invalid entity ID '{}'", id)))
+        }
+    }
+
+    /// Returns an `Err` unless `lang` looks like a language code (letters, digits, and `-`), so it can be used in a SPARQL query
+    fn check_sparql_language(lang: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+        if !lang.is_empty() && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            Ok(())
+        } else {
+            Err(From::from(format!("Invalid language code '{}'", lang)))
+        }
+    }
+
+    /// Returns the distribution of the lengths of the `lang` descriptions of all entities that use `property_id`,
+    /// as a map from length bucket (0, 10, 20, ...) to the number of entities. Entities without a description count as length 0
+    pub fn get_description_length_distribution(
+        &mut self,
+        property_id: &str,
+        lang: &str,
+    ) -> Result<HashMap<usize, u64>, Box<dyn ::std::error::Error>> {
+        Api::check_sparql_entity_id(property_id)?;
+        Api::check_sparql_language(lang)?;
+        let query = format!(
+            "SELECT ?len (COUNT(?item) AS ?count) WHERE {{ {{ SELECT DISTINCT ?item WHERE {{ ?item wdt:{} [] }} }} OPTIONAL {{ ?item schema:description ?desc . FILTER(LANG(?desc) = \"{}\") }} BIND(COALESCE(STRLEN(?desc), 0) AS ?len) }} GROUP BY ?len",
+            property_id, lang
+        );
+        let mut ret: HashMap<usize, u64> = HashMap::new();
+        for binding in self.sparql_bindings(&query)? {
+            let len: usize = match binding["len"]["value"]
+                .as_str()
+                .and_then(|l| l.parse().ok())
+            {
+                Some(len) => len,
+                None => continue,
+            };
+            let count: u64 = binding["count"]["value"]
+                .as_str()
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(0);
+            *ret.entry(len / 10 * 10).or_insert(0) += count;
+        }
+        Ok(ret)
+    }
+
+    /// Returns up to `limit` existing uses of a property, as (entity, value) pairs.
+    /// Values are parsed according to the data type of the property
+    pub fn get_wikidata_usage_examples(
+        &mut self,
+        property_id: &str,
+        limit: u32,
+    ) -> Result<Vec<(EntityId, DataValue)>, Box<dyn ::std::error::Error>> {
+        Api::check_sparql_entity_id(property_id)?;
+        let datatype = self.get_wikibase_property_type(property_id)?;
+        let query = format!(
+            "SELECT ?item ?value WHERE {{ ?item wdt:{} ?value }} LIMIT {}",
+            property_id, limit
+        );
+        Ok(self
+            .sparql_bindings(&query)?
+            .iter()
+            .filter_map(|b| {
+                let item = EntityId::from_uri(b["item"]["value"].as_str()?)?;
+                let value = DataValue::from_sparql(&b["value"], &datatype)?;
+                Some((item, value))
+            })
+            .collect())
+    }
+
+    /// Returns all properties, optionally only those of the property type `filter` (e.g. `ExternalId`),
+    /// and optionally at most `limit` of them
+    fn sparql_properties(
+        &mut self,
+        filter: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        let property_type = match filter {
+            Some(filter)
+                if !filter.is_empty() && filter.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                format!("wikibase:{}", filter)
+            }
+            Some(filter) => return Err(From::from(format!("Invalid property type '{}'", filter))),
+            None => "?t".to_string(),
+        };
+        let mut query = format!(
+            "SELECT ?p WHERE {{ ?p wikibase:propertyType {} }}",
+            property_type
+        );
+        if let Some(limit) = limit {
+            query += &format!(" LIMIT {}", limit);
+        }
+        Ok(self
+            .sparql_bindings(&query)?
+            .iter()
+            .filter_map(|b| EntityId::from_uri(b["p"]["value"].as_str()?))
+            .collect())
+    }
+
+    /// Returns up to `limit` properties, optionally only those of the property type `filter` (e.g. `ExternalId`)
+    pub fn get_all_wikidata_properties(
+        &mut self,
+        filter: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        self.sparql_properties(filter, Some(limit))
+    }
+
+    /// Returns all properties of the given data type
+    pub fn get_properties_by_datatype(
+        &mut self,
+        datatype: PropertyType,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        self.sparql_properties(Some(datatype.as_str()), None)
+    }
+
+    /// Returns up to `limit` items that have `value` as a (truthy) value for `property_id`
+    pub fn get_items_with_property_value(
+        &mut self,
+        property_id: &str,
+        value: &DataValue,
+        limit: u32,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        Api::check_sparql_entity_id(property_id)?;
+        match value {
+            DataValue::EntityId(id) => Api::check_sparql_entity_id(id.as_str())?,
+            DataValue::Monolingual { lang, .. } => Api::check_sparql_language(lang)?,
+            _ => {}
+        }
+        let query = format!(
+            "SELECT ?item WHERE {{ ?item wdt:{} {} }} LIMIT {}",
+            property_id,
+            value.to_sparql(),
+            limit
+        );
+        Ok(self
+            .sparql_bindings(&query)?
+            .iter()
+            .filter_map(|b| EntityId::from_uri(b["item"]["value"].as_str()?))
+            .collect())
+    }
+
+    /// Returns the example items of a property: the `P1855` (Wikidata property example) values of the property,
+    /// followed by up to `limit` items using the property from SPARQL, without duplicates.
+    /// If `constraint_item_id` is not empty, the SPARQL items are restricted to instances of that class
+    pub fn get_entity_example_items(
+        &mut self,
+        property_id: &str,
+        constraint_item_id: &str,
+        limit: u32,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        Api::check_sparql_entity_id(property_id)?;
+        if !constraint_item_id.is_empty() {
+            Api::check_sparql_entity_id(constraint_item_id)?;
+        }
+        let mut ret: Vec<EntityId> = self
+            .get_entity_data_value(property_id, "P1855", None)?
+            .into_iter()
+            .filter_map(|v| match v {
+                DataValue::EntityId(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+        let class_filter = if constraint_item_id.is_empty() {
+            "".to_string()
+        } else {
+            format!(" . ?item wdt:P31 wd:{}", constraint_item_id)
+        };
+        let query = format!(
+            "SELECT DISTINCT ?item WHERE {{ ?item wdt:{} []{} }} LIMIT {}",
+            property_id, class_filter, limit
+        );
+        for binding in self.sparql_bindings(&query)? {
+            if let Some(id) = binding["item"]["value"]
+                .as_str()
+                .and_then(EntityId::from_uri)
+            {
+                if !ret.contains(&id) {
+                    ret.push(id);
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns up to `limit` (subject, property) pairs of entities that have `entity_id` as a (truthy) value,
+    /// optionally only for `property_id`
+    pub fn get_entity_used_as_value(
+        &mut self,
+        entity_id: &str,
+        property_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<(EntityId, EntityId)>, Box<dyn ::std::error::Error>> {
+        Api::check_sparql_entity_id(entity_id)?;
+        if let Some(property_id) = property_id {
+            Api::check_sparql_entity_id(property_id)?;
+        }
+        let property_filter = match property_id {
+            Some(property_id) => format!(" VALUES ?property {{ wd:{} }}", property_id),
+            None => "".to_string(),
+        };
+        let query = format!(
+            "SELECT ?subject ?property WHERE {{{} ?property wikibase:directClaim ?wdt . ?subject ?wdt wd:{} }} LIMIT {}",
+            property_filter, entity_id, limit
+        );
+        Ok(self
+            .sparql_bindings(&query)?
+            .iter()
+            .filter_map(|b| {
+                let subject = EntityId::from_uri(b["subject"]["value"].as_str()?)?;
+                let property = EntityId::from_uri(b["property"]["value"].as_str()?)?;
+                Some((subject, property))
+            })
+            .collect())
+    }
+
+    /// Returns up to `limit` items that have sitelinks but no description in `lang`
+    pub fn get_items_missing_description(
+        &mut self,
+        lang: &str,
+        limit: u32,
+    ) -> Result<Vec<EntityId>, Box<dyn ::std::error::Error>> {
+        Api::check_sparql_language(lang)?;
+        let query = format!(
+            "SELECT ?item WHERE {{ ?item wikibase:sitelinks [] . FILTER NOT EXISTS {{ ?item schema:description ?desc . FILTER(LANG(?desc) = \"{}\") }} }} LIMIT {}",
+            lang, limit
+        );
+        Ok(self
+            .sparql_bindings(&query)?
+            .iter()
+            .filter_map(|b| EntityId::from_uri(b["item"]["value"].as_str()?))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Api, ContributionStats, EntityImportConfig, LogEvent, ParseSection, TalkSection};
+    use serde_json::json;
+
+    #[test]
+    fn site_info() {
+        let api = Api::new("https://www.wikidata.org/w/api.php").unwrap();
+        assert_eq!(
+            api.get_site_info_string("general", "sitename").unwrap(),
+            "Wikidata"
+        );
+    }
+
+    #[test]
+    fn sparql_query() {
+        let mut api = Api::new("https://www.wikidata.org/w/api.php").unwrap();
+        let res = api.sparql_query ( "SELECT ?q ?qLabel ?fellow_id { ?q wdt:P31 wd:Q5 ; wdt:P6594 ?fellow_id . SERVICE wikibase:label { bd:serviceParam wikibase:language '[AUTO_LANGUAGE],en'. } }" ).unwrap() ;
+        assert!(res["results"]["bindings"].as_array().unwrap().len() > 300);
+    }
+
+    #[test]
+    fn log_event_from_json() {
+        let j = serde_json::json!({"logid":123,"type":"protect","action":"protect","title":"Foo","ns":0,"user":"Bar","timestamp":"2019-01-01T00:00:00Z","comment":"Vandalism","params":{}});
+        let event = LogEvent::from_json(&j);
+        assert_eq!(event.logid, 123);
+        assert_eq!(event.log_type, "protect");
+        assert_eq!(event.title, "Foo");
+        assert_eq!(event.user, "Bar");
+    }
+
+    #[test]
+    fn contribution_stats() {
+        let mut stats = ContributionStats::default();
+        stats.add_contribution(&serde_json::json!({"ns":0,"sizediff":120,"new":""}));
+        stats.add_contribution(&serde_json::json!({"ns":0,"sizediff":-20,"minor":""}));
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.added_bytes, 120);
+        assert_eq!(stats.removed_bytes, 20);
+        assert_eq!(stats.minor_count, 1);
+        assert_eq!(stats.new_page_count, 1);
+    }
+
+    #[test]
+    fn parse_redirect_target() {
+        let keywords = vec!["#WEITERLEITUNG".to_string(), "#REDIRECT".to_string()];
+        assert_eq!(
+            Api::parse_redirect_target("#weiterleitung [[Berlin#Geschichte]]", &keywords),
+            Some("Berlin".to_string())
+        );
+        assert_eq!(
+            Api::parse_redirect_target("#REDIRECT:[[Foo|bar]]\n{{R from move}}", &keywords),
+            Some("Foo".to_string())
+        );
+        assert_eq!(Api::parse_redirect_target("Some [[text]]", &keywords), None);
+    }
+
+    #[test]
+    fn parse_version() {
+        assert_eq!(Api::parse_version("1.33.0-wmf.25"), Some((1, 33)));
+        assert_eq!(Api::parse_version("1.31.1"), Some((1, 31)));
+        assert_eq!(Api::parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn count_template_calls() {
+        let wikitext = "{{citation needed}} text {{Citation_needed|date=2019}} {{Cite web|url=x}}";
+        assert_eq!(
+            Api::count_template_calls(wikitext, "Template:Citation needed"),
+            2
+        );
+        assert_eq!(Api::count_template_calls(wikitext, "cite web"), 1);
+        assert_eq!(Api::count_template_calls(wikitext, "Cite"), 0);
+        let wikitext = "{{template:Foo}} {{ Vorlage : Foo |x}} {{Foo}}";
+        assert_eq!(Api::count_template_calls(wikitext, "Foo"), 2);
+        let prefixes = vec!["Vorlage".to_string(), "Template".to_string()];
+        assert_eq!(
+            Api::count_template_calls_with_prefixes(wikitext, "Vorlage:Foo", &prefixes),
+            3
+        );
+    }
+
+    #[test]
+    fn talk_section_from_wikitext() {
+        let text = "== Foo ==\nFirst. [[User:Alice|Alice]] ([[User talk:Alice|talk]]) 09:15, 3 March 2019 (UTC)\n:Reply. [[User:Bob/sig|Bob]] 12:34, 5 January 2020 (UTC)\n";
+        let section = TalkSection::from_wikitext("Foo", 1, text);
+        assert_eq!(section.last_user, Some("Bob".to_string()));
+        assert_eq!(
+            section.last_timestamp,
+            Some("2020-01-05T12:34:00Z".to_string())
+        );
+        assert!(!section.is_closed);
+        assert!(TalkSection::from_wikitext("Foo", 1, "{{Archive top}}\n").is_closed);
+        assert_eq!(
+            TalkSection::from_wikitext("Foo", 1, "Text —12:34, 5 May 2020 (UTC)").last_timestamp,
+            Some("2020-05-05T12:34:00Z".to_string())
+        );
+        assert!(Api::should_archive(&section, 30));
+        assert!(!Api::should_archive(
+            &TalkSection::from_wikitext("Foo", 1, "No signature"),
+            30
+        ));
+    }
+
+    #[test]
+    fn parse_disambiguation_targets() {
+        let text = "'''Foo''' may refer to:\n* [[Foo (band)|Foo]], a band\n* [[Foo River]]\n\n{{disambiguation}}";
+        assert_eq!(
+            Api::parse_disambiguation_targets(text),
+            vec!["Foo (band)".to_string(), "Foo River".to_string()]
+        );
+    }
+
+    #[test]
+    fn split_wikitext_sections() {
+        let section = |index: &str, level: u32, byteoffset: Option<usize>| ParseSection {
+            toclevel: 1,
+            level,
+            line: "".to_string(),
+            number: "".to_string(),
+            index: index.to_string(),
+            byteoffset,
+            anchor: "".to_string(),
+        };
+        let text = "Lead\n== A ==\na\n=== B ===\nb\n";
+        let sections = vec![
+            section("1", 2, Some(5)),
+            section("T-1", 2, None),
+            section("2", 3, Some(15)),
+        ];
+        let split = Api::split_wikitext_sections(sections, text);
+        let texts: Vec<(&str, &str)> = split
+            .iter()
+            .map(|(s, t)| (s.index.as_str(), t.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("0", "Lead\n"),
+                ("1", "== A ==\na\n=== B ===\nb\n"),
+                ("2", "=== B ===\nb\n")
+            ]
+        );
+    }
+
+    #[test]
+    fn linter_priority_from_site_info() {
+        let linter = json!({"high":["self-closed-tag"],"medium":["fostered"],"low":["obsolete-tag","duplicate-ids"]});
+        assert_eq!(
+            Api::linter_priority_from_site_info(&linter, "duplicate-ids"),
+            Some("low".to_string())
+        );
+        assert_eq!(
+            Api::linter_priority_from_site_info(&linter, "self-closed-tag"),
+            Some("high".to_string())
+        );
+        assert_eq!(
+            Api::linter_priority_from_site_info(&linter, "fosterd"),
+            None
+        );
+    }
+
+    #[test]
+    fn sitelink_translations() {
+        let sitelinks = json!({
+            "enwiki":{"site":"enwiki","title":"Douglas Adams"},
+            "dewiki":{"site":"dewiki","title":"Douglas Adams"},
+            "be_x_oldwiki":{"site":"be_x_oldwiki","title":"Дуглас Адамс"},
+            "commonswiki":{"site":"commonswiki","title":"Category:Douglas Adams"},
+            "enwikiquote":{"site":"enwikiquote","title":"Douglas Adams"}
+        });
+        let translations = Api::sitelink_translations(&sitelinks, "wiki", "en");
+        assert_eq!(translations.len(), 2);
+        assert_eq!(translations["de"], "Douglas Adams");
+        assert_eq!(translations["be-x-old"], "Дуглас Адамс");
+    }
+
+    #[test]
+    fn entity_import_wikitext() {
+        let entity = json!({
+            "labels":{"en":{"language":"en","value":"Douglas Adams"}},
+            "descriptions":{"de":{"language":"de","value":"Schriftsteller"}},
+            "claims":{
+                "P31":[
+                    {"id":"Q42$1","rank":"normal","mainsnak":{"property":"P31","datavalue":{"type":"wikibase-entityid","value":{"id":"Q5"}}}},
+                    {"id":"Q42$2","rank":"deprecated","mainsnak":{"property":"P31","datavalue":{"type":"wikibase-entityid","value":{"id":"Q1"}}}}
+                ],
+                "P625":[{"id":"Q42$3","rank":"normal","mainsnak":{"property":"P625","datavalue":{"type":"globecoordinate","value":{"latitude":52.5,"longitude":13.4}}}}]
+            }
+        });
+        let config = EntityImportConfig {
+            wikibase_api_url: "https://www.wikidata.org/w/api.php".to_string(),
+            label_template: Some("Label".to_string()),
+            description_template: Some("Description".to_string()),
+            property_templates: vec![
+                ("P31".to_string(), "Instance of".to_string()),
+                ("P625".to_string(), "Coordinates".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(
+            Api::entity_import_wikitext(&entity, &config, "en"),
+            "{{Label|Douglas Adams}}\n{{Instance of|Q5}}\n{{Coordinates|52.5,13.4}}"
+        );
+    }
+
+    #[test]
+    fn is_write_request() {
+        assert!(Api::is_write_request(
+            &hashmap!["action"=>"edit","title"=>"Foo"]
+        ));
+        assert!(Api::is_write_request(
+            &hashmap!["action"=>"purge","titles"=>"Foo","token"=>"+\\"]
+        ));
+        assert!(Api::is_write_request(
+            &hashmap!["action"=>"wbsetclaimvalue","claim"=>"Q1$x"]
+        ));
+        assert!(!Api::is_write_request(
+            &hashmap!["action"=>"login","lgname"=>"Foo","token"=>"x"]
+        ));
+        assert!(!Api::is_write_request(
+            &hashmap!["action"=>"query","meta"=>"tokens"]
+        ));
+    }
+
+    #[test]
+    fn block_allows_user_talk() {
+        let not_blocked = json!({"id":1,"name":"Foo Bar"});
+        assert!(Api::block_allows_user_talk(&not_blocked, "Baz"));
+        let blocked = json!({"id":1,"name":"Foo Bar","blockid":5,"blockedby":"Admin"});
+        assert!(Api::block_allows_user_talk(&blocked, "Foo_Bar"));
+        assert!(!Api::block_allows_user_talk(&blocked, "Baz"));
+        let blocked_own_talk = json!({"id":1,"name":"Foo Bar","blockid":5,"blockowntalk":""});
+        assert!(!Api::block_allows_user_talk(&blocked_own_talk, "Foo Bar"));
+        let partial = json!({"id":1,"name":"Foo Bar","blockid":5,"blockpartial":true});
+        assert!(Api::block_allows_user_talk(&partial, "Baz"));
+    }
+
+    #[test]
+    fn dry_run_result() {
+        let result = Api::dry_run_result(&hashmap!["action"=>"edit","title"=>"Foo"]);
+        assert_eq!(result["edit"]["result"], "Success");
+        assert!(result["entity"].is_null());
+
+        let result =
+            Api::dry_run_result(&hashmap!["action"=>"wbeditentity","new"=>"item","data"=>"{}"]);
+        assert_eq!(result["entity"]["id"], "Q0");
+
+        let data = r#"{"claims":[{"mainsnak":{"snaktype":"value","property":"P31"},"type":"statement"},{"mainsnak":{"snaktype":"value","property":"P31"},"type":"statement"}]}"#;
+        let result =
+            Api::dry_run_result(&hashmap!["action"=>"wbeditentity","id"=>"Q42","data"=>data]);
+        assert_eq!(result["entity"]["id"], "Q42");
+        assert_eq!(result["entity"]["claims"]["P31"][1]["id"], "Q42$dry-run-1");
+    }
+
+    #[test]
+    fn is_valid_entity_id() {
+        assert!(Api::is_valid_entity_id("Q42"));
+        assert!(Api::is_valid_entity_id("P31"));
+        assert!(Api::is_valid_entity_id("L7-F2"));
+        assert!(Api::is_valid_entity_id("L7-S1"));
+        assert!(!Api::is_valid_entity_id("Q"));
+        assert!(!Api::is_valid_entity_id("Q042"));
+        assert!(!Api::is_valid_entity_id("Q42-F1"));
+        assert!(!Api::is_valid_entity_id("L7-X1"));
+        assert!(!Api::is_valid_entity_id("Ö1"));
+    }
+
+    #[test]
+    fn is_valid_protection_expiry() {
+        assert!(Api::is_valid_protection_expiry("infinite"));
+        assert!(Api::is_valid_protection_expiry("2 weeks"));
+        assert!(Api::is_valid_protection_expiry("1 day"));
+        assert!(Api::is_valid_protection_expiry("2019-05-01T12:00:00Z"));
+        assert!(!Api::is_valid_protection_expiry("soon"));
+        assert!(!Api::is_valid_protection_expiry("2 fortnights"));
     }
 }
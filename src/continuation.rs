@@ -0,0 +1,203 @@
+/*!
+The `ContinuationIter` lazily walks through the results of a `list=` query,
+loading more results via the `continue` parameter when needed.
+The `CategoryTreeIter` walks through the pages of a category tree in the same way.
+*/
+
+use crate::api::Api;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The entries of one result batch, and the parameters to continue with, if any
+type Batch = (Vec<Value>, Option<HashMap<String, String>>);
+
+/// Iterates over the entries of a result array (usually `["query"][list]`) of a MediaWiki API query,
+/// fetching the next batch only once the current one is used up
+pub struct ContinuationIter<'a> {
+    api: &'a mut Api,
+    params: HashMap<String, String>,
+    path: Vec<String>,
+    cont: HashMap<String, String>,
+    buffer: VecDeque<Value>,
+    done: bool,
+}
+
+impl<'a> ContinuationIter<'a> {
+    /// Returns a new iterator for the `params` query, yielding the entries of `["query"][list]`
+    pub fn new(api: &'a mut Api, params: &HashMap<&str, &str>, list: &str) -> ContinuationIter<'a> {
+        ContinuationIter::new_with_path(api, params, &["query", list])
+    }
+
+    /// Returns a new iterator for the `params` query, yielding the entries of the array at `path`
+    /// in the result, e.g. `["query", "querypage", "results"]`
+    pub fn new_with_path(
+        api: &'a mut Api,
+        params: &HashMap<&str, &str>,
+        path: &[&str],
+    ) -> ContinuationIter<'a> {
+        ContinuationIter {
+            api,
+            params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+            cont: HashMap::new(),
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Loads the next batch of results into the buffer
+    fn load_next_batch(&mut self) -> Result<(), Box<dyn ::std::error::Error>> {
+        let mut params: HashMap<&str, &str> = self
+            .params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        for (k, v) in &self.cont {
+            params.insert(k, v);
+        }
+        let result = self.api.get_query_api_json(&params)?;
+        let (entries, cont) = ContinuationIter::parse_batch(&result, &self.path)?;
+        self.buffer.extend(entries);
+        match cont {
+            Some(cont) => self.cont = cont,
+            None => self.done = true,
+        }
+        Ok(())
+    }
+
+    /// Returns the entries at `path` in a query result, and its `continue` parameters, if any.
+    /// An API error response is returned as an `Err`
+    fn parse_batch(result: &Value, path: &[String]) -> Result<Batch, Box<dyn ::std::error::Error>> {
+        Api::check_api_error(result)?;
+        let cont = result["continue"].as_object().map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| Some((k.to_string(), v.as_str()?.to_string())))
+                .collect()
+        });
+        let entries = path.iter().fold(result, |v, key| &v[key]);
+        let entries = entries.as_array().cloned().unwrap_or_default();
+        Ok((entries, cont))
+    }
+}
+
+impl<'a> Iterator for ContinuationIter<'a> {
+    type Item = Result<Value, Box<dyn ::std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.buffer.pop_front() {
+                return Some(Ok(entry));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.load_next_batch() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Iterates over the pages and files in a category and its subcategories (breadth-first, up to `max_depth` levels below),
+/// loading one category at a time. Each category is visited once, and each page is returned once
+pub struct CategoryTreeIter<'a> {
+    api: &'a mut Api,
+    max_depth: u32,
+    queue: VecDeque<(String, u32)>,
+    visited: HashSet<String>,
+    seen_pages: HashSet<u64>,
+    buffer: VecDeque<Value>,
+}
+
+impl<'a> CategoryTreeIter<'a> {
+    /// Returns a new iterator for `category`, including the namespace prefix
+    pub fn new(api: &'a mut Api, category: &str, max_depth: u32) -> CategoryTreeIter<'a> {
+        let mut visited = HashSet::new();
+        visited.insert(category.to_string());
+        CategoryTreeIter {
+            api,
+            max_depth,
+            queue: vec![(category.to_string(), 0)].into_iter().collect(),
+            visited,
+            seen_pages: HashSet::new(),
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Loads all members of the next category in the queue, queueing its unvisited subcategories
+    fn load_next_category(&mut self) -> Result<(), Box<dyn ::std::error::Error>> {
+        let (category, depth) = match self.queue.pop_front() {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+        for member in self
+            .api
+            .get_category_members(&category, &["page", "subcat", "file"])
+        {
+            let member = member?;
+            if member["ns"].as_i64() == Some(14) {
+                let title = member["title"].as_str().unwrap_or("").to_string();
+                if depth < self.max_depth && self.visited.insert(title.clone()) {
+                    self.queue.push_back((title, depth + 1));
+                }
+            } else if let Some(pageid) = member["pageid"].as_u64() {
+                if self.seen_pages.insert(pageid) {
+                    self.buffer.push_back(member);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for CategoryTreeIter<'a> {
+    type Item = Result<Value, Box<dyn ::std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.buffer.pop_front() {
+                return Some(Ok(entry));
+            }
+            if self.queue.is_empty() {
+                return None;
+            }
+            if let Err(e) = self.load_next_category() {
+                self.queue.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContinuationIter;
+    use serde_json::json;
+
+    #[test]
+    fn parse_batch() {
+        let path = vec!["query".to_string(), "watchlistraw".to_string()];
+        let result = json!({"continue":{"wrcontinue":"0|Foo","continue":"-||"},"query":{"watchlistraw":[{"ns":0,"title":"Bar"}]}});
+        let (entries, cont) = ContinuationIter::parse_batch(&result, &path).unwrap();
+        assert_eq!(entries, vec![json!({"ns":0,"title":"Bar"})]);
+        assert_eq!(cont.unwrap()["wrcontinue"], "0|Foo");
+
+        let result = json!({"query":{"watchlistraw":[]}});
+        let (entries, cont) = ContinuationIter::parse_batch(&result, &path).unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(cont, None);
+
+        let result =
+            json!({"error":{"code":"bad_wltoken","info":"Incorrect watchlist token provided"}});
+        assert_eq!(
+            ContinuationIter::parse_batch(&result, &path)
+                .unwrap_err()
+                .to_string(),
+            "bad_wltoken: Incorrect watchlist token provided"
+        );
+    }
+}
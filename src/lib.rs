@@ -1,2 +1,7 @@
 pub mod api;
+pub mod continuation;
+pub mod pool;
+pub mod quickstatements;
 pub mod title;
+pub mod util;
+pub mod wikibase;
@@ -0,0 +1,94 @@
+/*!
+The `ApiPool` distributes queries round-robin across several `Api` instances.
+*/
+
+use crate::api::Api;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// `ApiPool` holds several `Api` instances, and uses them in turn for each query.
+/// All instances share the same http `Client`
+#[derive(Debug)]
+pub struct ApiPool {
+    apis: Vec<Api>,
+    next: usize,
+}
+
+impl ApiPool {
+    /// Returns a new `ApiPool` with one `Api` per URL
+    pub fn new(api_urls: &[&str]) -> Result<ApiPool, Box<dyn ::std::error::Error>> {
+        if api_urls.is_empty() {
+            return Err(From::from("ApiPool needs at least one API URL"));
+        }
+        let client = Arc::new(reqwest::Client::builder().build()?);
+        let mut apis = Vec::with_capacity(api_urls.len());
+        for api_url in api_urls {
+            apis.push(Api::new_with_client(api_url, client.clone())?);
+        }
+        Ok(ApiPool { apis, next: 0 })
+    }
+
+    /// Same as `new()`, but logs in to all `Api` instances
+    pub fn with_credentials(
+        api_urls: &[&str],
+        username: &str,
+        password: &str,
+    ) -> Result<ApiPool, Box<dyn ::std::error::Error>> {
+        let mut pool = ApiPool::new(api_urls)?;
+        for api in &mut pool.apis {
+            api.login(username, password)?;
+        }
+        Ok(pool)
+    }
+
+    /// Returns the number of `Api` instances in the pool
+    pub fn len(&self) -> usize {
+        self.apis.len()
+    }
+
+    /// Checks if the pool has no `Api` instances
+    pub fn is_empty(&self) -> bool {
+        self.apis.is_empty()
+    }
+
+    /// Returns the next `Api` instance to use
+    pub fn next_api(&mut self) -> &mut Api {
+        let num = self.next % self.apis.len();
+        self.next = (num + 1) % self.apis.len();
+        &mut self.apis[num]
+    }
+
+    /// Runs `Api::query_api_json` on the next `Api` instance
+    pub fn query_api_json(
+        &mut self,
+        params: &HashMap<&str, &str>,
+        method: &str,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        self.next_api().query_api_json(params, method)
+    }
+
+    /// Runs `Api::get_query_api_json` on the next `Api` instance
+    pub fn get_query_api_json(
+        &mut self,
+        params: &HashMap<&str, &str>,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        self.next_api().get_query_api_json(params)
+    }
+
+    /// Runs `Api::post_query_api_json` on the next `Api` instance
+    pub fn post_query_api_json(
+        &mut self,
+        params: &HashMap<&str, &str>,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        self.next_api().post_query_api_json(params)
+    }
+
+    /// Runs `Api::get_query_api_json_all` on the next `Api` instance
+    pub fn get_query_api_json_all(
+        &mut self,
+        params: &HashMap<&str, &str>,
+    ) -> Result<Value, Box<dyn ::std::error::Error>> {
+        self.next_api().get_query_api_json_all(params)
+    }
+}
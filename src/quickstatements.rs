@@ -0,0 +1,342 @@
+/*!
+Parses commands in the QuickStatements v2 format (one command per line, tab-separated).
+*/
+
+use serde_json::{json, Value};
+
+/// `QsValue` is a QuickStatements value, as a snak type, and the datavalue type and value for the API
+#[derive(Debug, Clone, PartialEq)]
+pub struct QsValue {
+    pub snaktype: String,
+    pub value_type: String,
+    pub value: Value,
+}
+
+impl QsValue {
+    /// Returns the value as snak JSON for the given property
+    pub fn to_snak(&self, property: &str) -> Value {
+        if self.snaktype != "value" {
+            return json!({"snaktype":self.snaktype,"property":property});
+        }
+        json!({"snaktype":"value","property":property,"datavalue":{"value":self.value,"type":self.value_type}})
+    }
+}
+
+/// `QsCommand` is a single parsed QuickStatements command.
+/// An `entity` of `LAST` refers to the entity created by the most recent `CREATE`
+#[derive(Debug, Clone, PartialEq)]
+pub enum QsCommand {
+    Create,
+    Claim {
+        entity: String,
+        property: String,
+        value: QsValue,
+        qualifiers: Vec<(String, QsValue)>,
+        references: Vec<(String, QsValue)>,
+    },
+    Label {
+        entity: String,
+        language: String,
+        text: String,
+    },
+    Description {
+        entity: String,
+        language: String,
+        text: String,
+    },
+    Alias {
+        entity: String,
+        language: String,
+        text: String,
+    },
+    Sitelink {
+        entity: String,
+        site: String,
+        title: String,
+    },
+}
+
+impl QsCommand {
+    /// Returns the name of the Wikibase API action used for this command
+    pub fn action(&self) -> &str {
+        match self {
+            QsCommand::Create => "wbeditentity",
+            QsCommand::Claim { .. } => "wbcreateclaim",
+            QsCommand::Label { .. } => "wbsetlabel",
+            QsCommand::Description { .. } => "wbsetdescription",
+            QsCommand::Alias { .. } => "wbsetaliases",
+            QsCommand::Sitelink { .. } => "wbsetsitelink",
+        }
+    }
+}
+
+/// Returns the text inside double quotes, or `None` if `s` is not quoted
+fn unquote(s: &str) -> Option<String> {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        Some(s[1..s.len() - 1].to_string())
+    } else {
+        None
+    }
+}
+
+/// Parses an entity ID value, e.g. `Q5`
+fn parse_entity_value(s: &str) -> Option<QsValue> {
+    let entity_type = match s.chars().next()? {
+        'Q' => "item",
+        'P' => "property",
+        'L' => "lexeme",
+        _ => return None,
+    };
+    let value = match s[1..].parse::<u64>() {
+        Ok(numeric_id) => json!({"entity-type":entity_type,"numeric-id":numeric_id,"id":s}),
+        Err(_) => json!({"id":s}),
+    };
+    Some(QsValue {
+        snaktype: "value".to_string(),
+        value_type: "wikibase-entityid".to_string(),
+        value,
+    })
+}
+
+/// Parses a time value, e.g. `+1967-01-17T00:00:00Z/11`
+fn parse_time_value(s: &str) -> Option<QsValue> {
+    let mut parts = s.splitn(2, '/');
+    let time = parts.next()?;
+    let precision: u64 = match parts.next() {
+        Some(p) => p.parse().ok()?,
+        None => 11,
+    };
+    Some(QsValue {
+        snaktype: "value".to_string(),
+        value_type: "time".to_string(),
+        value: json!({"time":time,"timezone":0,"before":0,"after":0,"precision":precision,"calendarmodel":"http://www.wikidata.org/entity/Q1985727"}),
+    })
+}
+
+/// Parses a coordinate value, e.g. `@52.5/13.4`
+fn parse_coordinate_value(s: &str) -> Option<QsValue> {
+    let mut parts = s[1..].splitn(2, '/');
+    let latitude: f64 = parts.next()?.parse().ok()?;
+    let longitude: f64 = parts.next()?.parse().ok()?;
+    Some(QsValue {
+        snaktype: "value".to_string(),
+        value_type: "globecoordinate".to_string(),
+        value: json!({"latitude":latitude,"longitude":longitude,"precision":0.000_001,"globe":"http://www.wikidata.org/entity/Q2"}),
+    })
+}
+
+/// Parses a quantity value, e.g. `+12.5` or `12.5U11573`.
+/// The amount is kept as written (with a `+` added if unsigned), as Wikibase stores it as a decimal string
+fn parse_quantity_value(s: &str) -> Option<QsValue> {
+    let mut parts = s.splitn(2, 'U');
+    let amount = parts.next()?;
+    let digits = amount.trim_start_matches(['+', '-']);
+    let mut number_parts = digits.splitn(2, '.');
+    let is_digits = |p: Option<&str>| match p {
+        Some(p) => !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()),
+        None => true,
+    };
+    if amount.len() - digits.len() > 1
+        || !is_digits(number_parts.next())
+        || !is_digits(number_parts.next())
+    {
+        return None;
+    }
+    let unit = match parts.next() {
+        Some(unit) => {
+            unit.parse::<u64>().ok()?;
+            format!("http://www.wikidata.org/entity/Q{}", unit)
+        }
+        None => "1".to_string(),
+    };
+    let amount = if amount.starts_with(['+', '-']) {
+        amount.to_string()
+    } else {
+        format!("+{}", amount)
+    };
+    Some(QsValue {
+        snaktype: "value".to_string(),
+        value_type: "quantity".to_string(),
+        value: json!({"amount":amount,"unit":unit}),
+    })
+}
+
+/// Parses a single QuickStatements value
+pub fn parse_value(s: &str) -> Result<QsValue, String> {
+    let s = s.trim();
+    if s == "somevalue" || s == "novalue" {
+        return Ok(QsValue {
+            snaktype: s.to_string(),
+            value_type: "".to_string(),
+            value: Value::Null,
+        });
+    }
+    if let Some(text) = unquote(s) {
+        return Ok(QsValue {
+            snaktype: "value".to_string(),
+            value_type: "string".to_string(),
+            value: json!(text),
+        });
+    }
+    if let Some(pos) = s.find(":\"") {
+        if let Some(text) = unquote(&s[pos + 1..]) {
+            return Ok(QsValue {
+                snaktype: "value".to_string(),
+                value_type: "monolingualtext".to_string(),
+                value: json!({"language":&s[..pos],"text":text}),
+            });
+        }
+    }
+    let parsed = if s.starts_with('@') {
+        parse_coordinate_value(s)
+    } else if (s.starts_with('+') || s.starts_with('-')) && s.contains('T') {
+        parse_time_value(s)
+    } else if s.starts_with(|c: char| c.is_ascii_uppercase()) {
+        parse_entity_value(s)
+    } else {
+        parse_quantity_value(s)
+    };
+    parsed.ok_or_else(|| format!("Can not parse value '{}'", s))
+}
+
+/// Parses a single line of QuickStatements v2.
+/// Returns `None` for empty lines
+pub fn parse_line(line: &str) -> Result<Option<QsCommand>, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line == "CREATE" {
+        return Ok(Some(QsCommand::Create));
+    }
+    let parts: Vec<&str> = line.split('\t').map(|p| p.trim()).collect();
+    if parts.len() < 3 {
+        return Err(format!("Not enough columns in '{}'", line));
+    }
+    if parts[0].starts_with('-') {
+        return Err("Removing statements is not supported".to_string());
+    }
+    let entity = parts[0].to_string();
+    let key = parts[1];
+    let text =
+        || unquote(parts[2]).ok_or_else(|| format!("Expected quoted text, got '{}'", parts[2]));
+    let command = match key.chars().next() {
+        Some('L') if key.len() > 1 => QsCommand::Label {
+            entity,
+            language: key[1..].to_string(),
+            text: text()?,
+        },
+        Some('D') if key.len() > 1 => QsCommand::Description {
+            entity,
+            language: key[1..].to_string(),
+            text: text()?,
+        },
+        Some('A') if key.len() > 1 => QsCommand::Alias {
+            entity,
+            language: key[1..].to_string(),
+            text: text()?,
+        },
+        Some('S') if key.len() > 1 => QsCommand::Sitelink {
+            entity,
+            site: key[1..].to_string(),
+            title: text()?,
+        },
+        Some('L') | Some('D') | Some('A') | Some('S') => {
+            return Err(format!("Missing language or site in '{}'", key))
+        }
+        Some('P') => {
+            let value = parse_value(parts[2])?;
+            let mut qualifiers = vec![];
+            let mut references = vec![];
+            for pair in parts[3..].chunks(2) {
+                if pair.len() != 2 {
+                    return Err(format!("Missing value for '{}'", pair[0]));
+                }
+                let value = parse_value(pair[1])?;
+                match pair[0].chars().next() {
+                    Some('P') => qualifiers.push((pair[0].to_string(), value)),
+                    Some('S') => references.push((format!("P{}", &pair[0][1..]), value)),
+                    _ => return Err(format!("Unknown qualifier or source '{}'", pair[0])),
+                }
+            }
+            QsCommand::Claim {
+                entity,
+                property: key.to_string(),
+                value,
+                qualifiers,
+                references,
+            }
+        }
+        _ => return Err(format!("Unknown command '{}'", key)),
+    };
+    Ok(Some(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, parse_value, QsCommand};
+
+    #[test]
+    fn parse_quickstatements() {
+        assert_eq!(parse_line("CREATE").unwrap(), Some(QsCommand::Create));
+        assert_eq!(
+            parse_line("LAST\tLen\t\"Douglas Adams\"").unwrap(),
+            Some(QsCommand::Label {
+                entity: "LAST".to_string(),
+                language: "en".to_string(),
+                text: "Douglas Adams".to_string()
+            })
+        );
+        match parse_line("Q42\tP31\tQ5\tP580\t+1952-03-11T00:00:00Z/11\tS143\tQ328").unwrap() {
+            Some(QsCommand::Claim {
+                qualifiers,
+                references,
+                ..
+            }) => {
+                assert_eq!(qualifiers[0].0, "P580");
+                assert_eq!(qualifiers[0].1.value_type, "time");
+                assert_eq!(references[0].0, "P143");
+            }
+            x => panic!("Unexpected result {:?}", x),
+        }
+        assert!(parse_line("-Q42\tP31\tQ5").is_err());
+    }
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(parse_value("\"abc\"").unwrap().value_type, "string");
+        assert_eq!(
+            parse_value("en:\"abc\"").unwrap().value_type,
+            "monolingualtext"
+        );
+        assert_eq!(
+            parse_value("@52.5/13.4").unwrap().value_type,
+            "globecoordinate"
+        );
+        assert_eq!(
+            parse_value("12.5U11573").unwrap().value["unit"],
+            "http://www.wikidata.org/entity/Q11573"
+        );
+        assert_eq!(parse_value("novalue").unwrap().snaktype, "novalue");
+        assert_eq!(parse_value("12.50").unwrap().value["amount"], "+12.50");
+        assert_eq!(
+            parse_value("-12345678901234567890U11573").unwrap().value["amount"],
+            "-12345678901234567890"
+        );
+        assert!(parse_value("12.").is_err());
+        assert!(parse_value("+-1").is_err());
+        assert!(parse_value("1e5").is_err());
+    }
+
+    #[test]
+    fn parse_line_without_language_or_site() {
+        for line in &[
+            "Q1\tL\t\"x\"",
+            "Q1\tD\t\"x\"",
+            "Q1\tA\t\"x\"",
+            "Q1\tS\t\"x\"",
+        ] {
+            assert!(parse_line(line).is_err(), "{}", line);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 #[derive(Debug)]
 /// Under development
+#[allow(dead_code)]
 pub struct Title {
     title: String,
     namespace_id: u32,
@@ -11,7 +12,7 @@ impl Title {
     pub fn new(title: &str, namespace_id: u32) -> Title {
         Title {
             title: title.to_string(),
-            namespace_id: namespace_id,
+            namespace_id,
         }
     }
 }
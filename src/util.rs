@@ -0,0 +1,120 @@
+/*!
+Helper functions for working with MediaWiki API results.
+*/
+
+use serde_json::Value;
+
+/// `JsonPatch` is a single difference between two JSON values, at a dot-separated `path`
+/// (e.g. `query.pages.123.title`; array elements by index, the top level as an empty path)
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonPatch {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        old: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+/// Returns `key` appended to `path`
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Adds the differences between `a` and `b` at `path` to `patches`
+fn diff_at(path: &str, a: &Value, b: &Value, patches: &mut Vec<JsonPatch>) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, old) in a {
+                match b.get(k) {
+                    Some(new) => diff_at(&join_path(path, k), old, new, patches),
+                    None => patches.push(JsonPatch::Removed {
+                        path: join_path(path, k),
+                        old: old.clone(),
+                    }),
+                }
+            }
+            for (k, value) in b {
+                if !a.contains_key(k) {
+                    patches.push(JsonPatch::Added {
+                        path: join_path(path, k),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, old) in a.iter().enumerate() {
+                match b.get(i) {
+                    Some(new) => diff_at(&join_path(path, &i.to_string()), old, new, patches),
+                    None => patches.push(JsonPatch::Removed {
+                        path: join_path(path, &i.to_string()),
+                        old: old.clone(),
+                    }),
+                }
+            }
+            for (i, value) in b.iter().enumerate().skip(a.len()) {
+                patches.push(JsonPatch::Added {
+                    path: join_path(path, &i.to_string()),
+                    value: value.clone(),
+                });
+            }
+        }
+        (a, b) => {
+            if a != b {
+                patches.push(JsonPatch::Changed {
+                    path: path.to_string(),
+                    old: a.clone(),
+                    new: b.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Returns the differences between two JSON values, e.g. two results of the same API query.
+/// Objects and arrays are compared recursively; array elements are compared by index
+pub fn json_diff(a: &Value, b: &Value) -> Vec<JsonPatch> {
+    let mut patches = vec![];
+    diff_at("", a, b, &mut patches);
+    patches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_diff, JsonPatch};
+    use serde_json::json;
+
+    #[test]
+    fn json_diff_objects() {
+        let a = json!({"query":{"pages":[{"title":"Foo"}],"old":1}});
+        let b = json!({"query":{"pages":[{"title":"Bar"},{"title":"Baz"}],"new":2}});
+        let patches = json_diff(&a, &b);
+        assert_eq!(patches.len(), 4);
+        assert!(patches.contains(&JsonPatch::Changed {
+            path: "query.pages.0.title".to_string(),
+            old: json!("Foo"),
+            new: json!("Bar")
+        }));
+        assert!(patches.contains(&JsonPatch::Added {
+            path: "query.pages.1".to_string(),
+            value: json!({"title":"Baz"})
+        }));
+        assert!(patches.contains(&JsonPatch::Removed {
+            path: "query.old".to_string(),
+            old: json!(1)
+        }));
+        assert!(json_diff(&a, &a).is_empty());
+    }
+}
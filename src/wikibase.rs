@@ -0,0 +1,531 @@
+/*!
+Data types for Wikibase entities, as returned by the Wikibase API modules.
+*/
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// The rank of a Wikibase claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimRank {
+    Preferred,
+    Normal,
+    Deprecated,
+}
+
+impl ClaimRank {
+    /// Returns the rank for its API name (`preferred`, `normal`, `deprecated`)
+    pub fn from_name(rank: &str) -> Option<ClaimRank> {
+        match rank {
+            "preferred" => Some(ClaimRank::Preferred),
+            "normal" => Some(ClaimRank::Normal),
+            "deprecated" => Some(ClaimRank::Deprecated),
+            _ => None,
+        }
+    }
+
+    /// Returns the API name of the rank
+    pub fn as_str(&self) -> &str {
+        match self {
+            ClaimRank::Preferred => "preferred",
+            ClaimRank::Normal => "normal",
+            ClaimRank::Deprecated => "deprecated",
+        }
+    }
+}
+
+/// `Claim` is a single Wikibase statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    pub id: String,
+    pub property: String,
+    pub rank: ClaimRank,
+    pub mainsnak: Value,
+    pub qualifiers: Value,
+    pub references: Value,
+}
+
+impl Claim {
+    /// Creates a `Claim` from the JSON of a single statement
+    pub fn from_json(j: &Value) -> Option<Claim> {
+        Some(Claim {
+            id: j["id"].as_str()?.to_string(),
+            property: j["mainsnak"]["property"].as_str()?.to_string(),
+            rank: ClaimRank::from_name(j["rank"].as_str()?)?,
+            mainsnak: j["mainsnak"].clone(),
+            qualifiers: j["qualifiers"].clone(),
+            references: j["references"].clone(),
+        })
+    }
+}
+
+/// `EntityId` is the ID of a Wikibase entity, e.g. `Q42` or `P31`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityId(pub String);
+
+impl EntityId {
+    /// Returns a new `EntityId`
+    pub fn new(id: &str) -> EntityId {
+        EntityId(id.to_string())
+    }
+
+    /// Returns the entity ID from an entity URI, e.g. `http://www.wikidata.org/entity/Q42`
+    pub fn from_uri(uri: &str) -> Option<EntityId> {
+        let id = uri.rsplit('/').next()?;
+        if id.is_empty() {
+            None
+        } else {
+            Some(EntityId::new(id))
+        }
+    }
+
+    /// Returns the ID as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ::std::fmt::Display for EntityId {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// `PropertyType` is the data type of a Wikibase property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyType {
+    WikibaseItem,
+    WikibaseProperty,
+    WikibaseLexeme,
+    WikibaseForm,
+    WikibaseSense,
+    String,
+    ExternalId,
+    Url,
+    CommonsMedia,
+    Monolingualtext,
+    Quantity,
+    Time,
+    GlobeCoordinate,
+    Math,
+    GeoShape,
+    TabularData,
+    MusicalNotation,
+    EntitySchema,
+}
+
+impl PropertyType {
+    /// Returns the name of the type in the Wikibase ontology, as used with `wikibase:propertyType`
+    pub fn as_str(&self) -> &str {
+        match self {
+            PropertyType::WikibaseItem => "WikibaseItem",
+            PropertyType::WikibaseProperty => "WikibaseProperty",
+            PropertyType::WikibaseLexeme => "WikibaseLexeme",
+            PropertyType::WikibaseForm => "WikibaseForm",
+            PropertyType::WikibaseSense => "WikibaseSense",
+            PropertyType::String => "String",
+            PropertyType::ExternalId => "ExternalId",
+            PropertyType::Url => "Url",
+            PropertyType::CommonsMedia => "CommonsMedia",
+            PropertyType::Monolingualtext => "Monolingualtext",
+            PropertyType::Quantity => "Quantity",
+            PropertyType::Time => "Time",
+            PropertyType::GlobeCoordinate => "GlobeCoordinate",
+            PropertyType::Math => "Math",
+            PropertyType::GeoShape => "GeoShape",
+            PropertyType::TabularData => "TabularData",
+            PropertyType::MusicalNotation => "MusicalNotation",
+            PropertyType::EntitySchema => "EntitySchema",
+        }
+    }
+}
+
+/// `DataValue` is the value of a Wikibase snak
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue {
+    EntityId(EntityId),
+    String(String),
+    Quantity { amount: f64, unit: Option<EntityId> },
+    Time { value: String, precision: u8 },
+    Coordinate { lat: f64, lon: f64 },
+    Monolingual { lang: String, text: String },
+}
+
+impl DataValue {
+    /// Creates a `DataValue` from the JSON `datavalue` object of a snak
+    pub fn from_json(j: &Value) -> Option<DataValue> {
+        let v = &j["value"];
+        match j["type"].as_str()? {
+            "wikibase-entityid" => Some(DataValue::EntityId(EntityId::new(v["id"].as_str()?))),
+            "string" => Some(DataValue::String(v.as_str()?.to_string())),
+            "quantity" => Some(DataValue::Quantity {
+                amount: v["amount"].as_str()?.parse().ok()?,
+                unit: match v["unit"].as_str() {
+                    Some("1") | None => None,
+                    Some(unit) => EntityId::from_uri(unit),
+                },
+            }),
+            "time" => Some(DataValue::Time {
+                value: v["time"].as_str()?.to_string(),
+                precision: v["precision"].as_u64()? as u8,
+            }),
+            "globecoordinate" => Some(DataValue::Coordinate {
+                lat: v["latitude"].as_f64()?,
+                lon: v["longitude"].as_f64()?,
+            }),
+            "monolingualtext" => Some(DataValue::Monolingual {
+                lang: v["language"].as_str()?.to_string(),
+                text: v["text"].as_str()?.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Creates a `DataValue` from a SPARQL result binding of a `wdt:` value, for a property of `datatype`.
+    /// Truthy values carry no quantity unit or time precision, so those are `None` and day precision (11)
+    pub fn from_sparql(binding: &Value, datatype: &str) -> Option<DataValue> {
+        let v = binding["value"].as_str()?;
+        match datatype {
+            "wikibase-item" | "wikibase-property" | "wikibase-lexeme" | "wikibase-form"
+            | "wikibase-sense" => Some(DataValue::EntityId(EntityId::from_uri(v)?)),
+            "quantity" => Some(DataValue::Quantity {
+                amount: v.parse().ok()?,
+                unit: None,
+            }),
+            "time" => Some(DataValue::Time {
+                value: v.to_string(),
+                precision: 11,
+            }),
+            "globe-coordinate" => {
+                let point = v.trim_start_matches("Point(").trim_end_matches(')');
+                let mut parts = point.split_whitespace();
+                let lon = parts.next()?.parse().ok()?;
+                let lat = parts.next()?.parse().ok()?;
+                Some(DataValue::Coordinate { lat, lon })
+            }
+            "monolingualtext" => Some(DataValue::Monolingual {
+                lang: binding["xml:lang"].as_str()?.to_string(),
+                text: v.to_string(),
+            }),
+            _ => Some(DataValue::String(v.to_string())),
+        }
+    }
+
+    /// Returns the value as a JSON `datavalue` object, as used in snaks.
+    /// Units and calendar models refer to Wikidata entities
+    pub fn to_json(&self) -> Value {
+        match self {
+            DataValue::EntityId(id) => {
+                let entity_type = match id.as_str().chars().next() {
+                    Some('P') => "property",
+                    Some('L') if id.as_str().contains("-F") => "form",
+                    Some('L') if id.as_str().contains("-S") => "sense",
+                    Some('L') => "lexeme",
+                    Some('M') => "mediainfo",
+                    _ => "item",
+                };
+                json!({"type":"wikibase-entityid","value":{"entity-type":entity_type,"id":id.as_str()}})
+            }
+            DataValue::String(s) => json!({"type":"string","value":s}),
+            DataValue::Quantity { amount, unit } => {
+                let amount = if *amount < 0.0 {
+                    amount.to_string()
+                } else {
+                    format!("+{}", amount)
+                };
+                let unit = match unit {
+                    Some(unit) => format!("http://www.wikidata.org/entity/{}", unit),
+                    None => "1".to_string(),
+                };
+                json!({"type":"quantity","value":{"amount":amount,"unit":unit}})
+            }
+            DataValue::Time { value, precision } => {
+                json!({"type":"time","value":{"time":value,"timezone":0,"before":0,"after":0,"precision":precision,"calendarmodel":"http://www.wikidata.org/entity/Q1985727"}})
+            }
+            DataValue::Coordinate { lat, lon } => {
+                json!({"type":"globecoordinate","value":{"latitude":lat,"longitude":lon,"precision":0.000_001,"globe":"http://www.wikidata.org/entity/Q2"}})
+            }
+            DataValue::Monolingual { lang, text } => {
+                json!({"type":"monolingualtext","value":{"language":lang,"text":text}})
+            }
+        }
+    }
+
+    /// Returns the value as a SPARQL term, as it appears as the object of a `wdt:` triple
+    pub fn to_sparql(&self) -> String {
+        let literal = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        match self {
+            DataValue::EntityId(id) => format!("wd:{}", id),
+            DataValue::String(s) => literal(s),
+            DataValue::Quantity { amount, .. } => {
+                let amount = amount.to_string();
+                format!("\"{}\"^^xsd:decimal", amount.trim_start_matches('+'))
+            }
+            DataValue::Time { value, .. } => {
+                format!("{}^^xsd:dateTime", literal(value.trim_start_matches('+')))
+            }
+            DataValue::Coordinate { lat, lon } => {
+                format!("\"Point({} {})\"^^geo:wktLiteral", lon, lat)
+            }
+            DataValue::Monolingual { lang, text } => format!("{}@{}", literal(text), lang),
+        }
+    }
+}
+
+/// `Snak` is a property with a value (`snaktype` `value`), or without one (`somevalue` or `novalue`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snak {
+    pub property: EntityId,
+    pub snaktype: String,
+    pub datavalue: Option<DataValue>,
+}
+
+impl Snak {
+    /// Returns a new `Snak` with a value
+    pub fn new(property: &str, value: DataValue) -> Snak {
+        Snak {
+            property: EntityId::new(property),
+            snaktype: "value".to_string(),
+            datavalue: Some(value),
+        }
+    }
+
+    /// Returns the snak as JSON for the Wikibase API
+    pub fn to_json(&self) -> Value {
+        let mut ret = json!({"snaktype":self.snaktype,"property":self.property.as_str()});
+        if let Some(datavalue) = &self.datavalue {
+            ret["datavalue"] = datavalue.to_json();
+        }
+        ret
+    }
+
+    /// Returns the snaks as a JSON object of snak arrays, grouped by property
+    fn group_to_json(snaks: &[Snak]) -> Value {
+        let mut ret = json!({});
+        for snak in snaks {
+            let entry = &mut ret[snak.property.as_str()];
+            if entry.is_null() {
+                *entry = json!([]);
+            }
+            if let Some(entry) = entry.as_array_mut() {
+                entry.push(snak.to_json());
+            }
+        }
+        ret
+    }
+}
+
+/// `ClaimData` is a new claim to be added to an entity
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaimData {
+    pub snak: Snak,
+    pub rank: ClaimRank,
+    pub qualifiers: Vec<Snak>,
+    pub references: Vec<Vec<Snak>>,
+}
+
+impl ClaimData {
+    /// Returns the claim as JSON for `wbeditentity`
+    pub fn to_json(&self) -> Value {
+        let mut ret =
+            json!({"mainsnak":self.snak.to_json(),"type":"statement","rank":self.rank.as_str()});
+        if !self.qualifiers.is_empty() {
+            ret["qualifiers"] = Snak::group_to_json(&self.qualifiers);
+        }
+        if !self.references.is_empty() {
+            let references: Vec<Value> = self
+                .references
+                .iter()
+                .map(|snaks| json!({ "snaks": Snak::group_to_json(snaks) }))
+                .collect();
+            ret["references"] = json!(references);
+        }
+        ret
+    }
+}
+
+/// `ConstraintViolation` is a single result of `wbcheckconstraints` that is not compliant
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintViolation {
+    pub property: String,
+    pub constraint_type: String,
+    pub message: String,
+    pub status: String,
+}
+
+impl ConstraintViolation {
+    /// Creates a `ConstraintViolation` from a single constraint check result object
+    pub fn from_json(j: &Value) -> ConstraintViolation {
+        ConstraintViolation {
+            property: j["property"].as_str().unwrap_or("").to_string(),
+            constraint_type: j["constraint"]["type"].as_str().unwrap_or("").to_string(),
+            message: j["message-html"].as_str().unwrap_or("").to_string(),
+            status: j["status"].as_str().unwrap_or("").to_string(),
+        }
+    }
+}
+
+/// `PropertyConstraint` is a constraint of a Wikidata property, from a `P2302` (property constraint) claim
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyConstraint {
+    pub constraint_type: EntityId,
+    pub status: String,
+    pub parameters: HashMap<EntityId, Vec<DataValue>>,
+}
+
+impl PropertyConstraint {
+    /// Creates a `PropertyConstraint` from a `P2302` claim.
+    /// The `P2316` (constraint status) qualifier becomes the `status` (`mandatory`, `suggestion`, or `normal`);
+    /// all other qualifiers are constraint parameters
+    pub fn from_claim(claim: &Claim) -> Option<PropertyConstraint> {
+        let constraint_type = match DataValue::from_json(&claim.mainsnak["datavalue"])? {
+            DataValue::EntityId(id) => id,
+            _ => return None,
+        };
+        let mut status = "normal".to_string();
+        let mut parameters: HashMap<EntityId, Vec<DataValue>> = HashMap::new();
+        if let Some(qualifiers) = claim.qualifiers.as_object() {
+            for (property, snaks) in qualifiers {
+                let values: Vec<DataValue> = snaks
+                    .as_array()
+                    .map(|snaks| {
+                        snaks
+                            .iter()
+                            .filter_map(|snak| DataValue::from_json(&snak["datavalue"]))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if property == "P2316" {
+                    status = match values.first() {
+                        Some(DataValue::EntityId(id)) if id.as_str() == "Q21502408" => "mandatory",
+                        Some(DataValue::EntityId(id)) if id.as_str() == "Q62026391" => "suggestion",
+                        _ => "normal",
+                    }
+                    .to_string();
+                } else {
+                    parameters.insert(EntityId::new(property), values);
+                }
+            }
+        }
+        Some(PropertyConstraint {
+            constraint_type,
+            status,
+            parameters,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Claim, DataValue, EntityId, PropertyConstraint};
+
+    #[test]
+    fn data_value_from_json() {
+        let j = serde_json::json!({"type":"quantity","value":{"amount":"+12.5","unit":"http://www.wikidata.org/entity/Q11573"}});
+        assert_eq!(
+            DataValue::from_json(&j),
+            Some(DataValue::Quantity {
+                amount: 12.5,
+                unit: Some(EntityId::new("Q11573"))
+            })
+        );
+        let j = serde_json::json!({"type":"wikibase-entityid","value":{"entity-type":"item","id":"Q5"}});
+        assert_eq!(
+            DataValue::from_json(&j),
+            Some(DataValue::EntityId(EntityId::new("Q5")))
+        );
+    }
+
+    #[test]
+    fn data_value_to_json() {
+        let values = vec![
+            DataValue::EntityId(EntityId::new("Q5")),
+            DataValue::Quantity {
+                amount: -3.5,
+                unit: Some(EntityId::new("Q11573")),
+            },
+            DataValue::Time {
+                value: "+1952-03-11T00:00:00Z".to_string(),
+                precision: 11,
+            },
+            DataValue::Coordinate {
+                lat: 52.5,
+                lon: 13.4,
+            },
+        ];
+        for value in values {
+            assert_eq!(DataValue::from_json(&value.to_json()), Some(value));
+        }
+    }
+
+    #[test]
+    fn data_value_to_sparql() {
+        assert_eq!(
+            DataValue::EntityId(EntityId::new("Q5")).to_sparql(),
+            "wd:Q5"
+        );
+        assert_eq!(
+            DataValue::String("a \"b\"".to_string()).to_sparql(),
+            "\"a \\\"b\\\"\""
+        );
+        assert_eq!(
+            DataValue::Monolingual {
+                lang: "en".to_string(),
+                text: "Foo".to_string()
+            }
+            .to_sparql(),
+            "\"Foo\"@en"
+        );
+        assert_eq!(
+            DataValue::Time {
+                value: "+1952-03-11T00:00:00Z".to_string(),
+                precision: 11
+            }
+            .to_sparql(),
+            "\"1952-03-11T00:00:00Z\"^^xsd:dateTime"
+        );
+        assert_eq!(
+            DataValue::Quantity {
+                amount: 42.0,
+                unit: None
+            }
+            .to_sparql(),
+            "\"42\"^^xsd:decimal"
+        );
+    }
+
+    #[test]
+    fn data_value_from_sparql() {
+        let j = serde_json::json!({"type":"literal","datatype":"http://www.opengis.net/ont/geosparql#wktLiteral","value":"Point(13.4 52.5)"});
+        assert_eq!(
+            DataValue::from_sparql(&j, "globe-coordinate"),
+            Some(DataValue::Coordinate {
+                lat: 52.5,
+                lon: 13.4
+            })
+        );
+        let j = serde_json::json!({"type":"uri","value":"http://www.wikidata.org/entity/Q5"});
+        assert_eq!(
+            DataValue::from_sparql(&j, "wikibase-item"),
+            Some(DataValue::EntityId(EntityId::new("Q5")))
+        );
+    }
+
+    #[test]
+    fn property_constraint_from_claim() {
+        let item = |id: &str| serde_json::json!([{"snaktype":"value","datavalue":{"type":"wikibase-entityid","value":{"id":id}}}]);
+        let j = serde_json::json!({
+            "id":"P31$1","rank":"normal",
+            "mainsnak":{"property":"P2302","datavalue":{"type":"wikibase-entityid","value":{"id":"Q21503250"}}},
+            "qualifiers":{"P2308":item("Q5"),"P2316":item("Q21502408")}
+        });
+        let constraint = PropertyConstraint::from_claim(&Claim::from_json(&j).unwrap()).unwrap();
+        assert_eq!(constraint.constraint_type, EntityId::new("Q21503250"));
+        assert_eq!(constraint.status, "mandatory");
+        assert_eq!(
+            constraint.parameters[&EntityId::new("P2308")],
+            vec![DataValue::EntityId(EntityId::new("Q5"))]
+        );
+        assert!(!constraint.parameters.contains_key(&EntityId::new("P2316")));
+    }
+}